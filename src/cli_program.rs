@@ -1,9 +1,513 @@
-use crate::config::{Config, Record, RecordType, CONFIG_SINGLETON};
-use crate::dns_provider::DnsProvider;
+use std::collections::{HashMap, HashSet};
 
-use crate::ip_handler::{get_current_ip, get_last_ip, save_ip};
-use crate::webhook_notifier::WebhookNotifier;
-use crate::webhook_notifier::WebhookNotifierType::DiscordWebhook;
+use chrono::Utc;
+use serde::{Serialize, Serializer};
+
+use crate::config::{
+    find_zone_id, Config, ProviderAccount, ProviderKind, Record, RecordSettings, RecordType,
+    CONFIG_SINGLETON,
+};
+use crate::desired_state::{diff, same_value, Change, DesiredState, PlannedRecord, ZoneDiff};
+use crate::dns_provider::{DnsProvider, DomainDetails};
+use crate::error::DnsCliError;
+
+use crate::interaction;
+use crate::ip_handler::{get_current_ip, IpVersion};
+use crate::output::{print_table, status, Output};
+use crate::record_content::{validate_record, RecordContent};
+use crate::state::{RecordState, State};
+use crate::webhook_notifier::{IpChange, NotifyEvent, WebhookNotifier};
+use crate::zone_file;
+
+/// The result of updating a single record during a check
+#[derive(Serialize)]
+struct RecordOutcome {
+    #[serde(skip)]
+    key: String,
+    account: String,
+    name: String,
+    record_type: RecordType,
+    #[serde(flatten)]
+    status: RecordStatus,
+    /// The content found at the provider when reconciling showed it differed from the IP
+    drift: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "snake_case")]
+enum RecordStatus {
+    Updated,
+    /// Reconciling found the record already points to the IP
+    InSync,
+    Failed(#[serde(serialize_with = "serialize_error")] DnsCliError),
+}
+
+fn serialize_error<S: Serializer>(err: &DnsCliError, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(err)
+}
+
+/// The IP of one version found during a check
+#[derive(Serialize)]
+struct IpCheck {
+    version: IpVersion,
+    ip: String,
+    /// The IP of the check before, None on the first check
+    previous_ip: Option<String>,
+    changed: bool,
+}
+
+/// An IP version whose check stopped early, the other version is still checked
+#[derive(Serialize)]
+struct IpFailure {
+    version: IpVersion,
+    #[serde(serialize_with = "serialize_error")]
+    error: DnsCliError,
+}
+
+/// What a check found and did
+#[derive(Serialize)]
+pub struct CheckReport {
+    ips: Vec<IpCheck>,
+    records: Vec<RecordOutcome>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    failures: Vec<IpFailure>,
+}
+
+impl CheckReport {
+    fn add(
+        &mut self,
+        version: IpVersion,
+        result: Result<(IpCheck, Vec<RecordOutcome>), DnsCliError>,
+    ) {
+        match result {
+            Ok((ip, outcomes)) => {
+                self.ips.push(ip);
+                self.records.extend(outcomes);
+            }
+            Err(error) => self.failures.push(IpFailure { version, error }),
+        }
+    }
+
+    /// Fails with the error of the first IP version that could not be checked, otherwise with
+    /// the number of records that could not be updated
+    pub fn result(self) -> Result<(), DnsCliError> {
+        if let Some(failure) = self.failures.into_iter().next() {
+            return Err(failure.error);
+        }
+        let failed = self.records.iter().filter(|o| o.failed()).count();
+        if failed > 0 {
+            return Err(DnsCliError::RecordsFailed(failed));
+        }
+        Ok(())
+    }
+}
+
+impl Output for CheckReport {
+    fn print_table(&self) {
+        if !self.records.is_empty() {
+            print_summary(&self.records);
+        }
+    }
+}
+
+impl RecordOutcome {
+    fn failed(&self) -> bool {
+        matches!(self.status, RecordStatus::Failed(_))
+    }
+}
+
+/// Identifies a record over all accounts, used to remember which records failed to update
+fn record_key(account: &ProviderAccount, record: &Record) -> String {
+    format!("{}/{}", account.name, record.id)
+}
+
+fn is_key_of(key: &str, account: &ProviderAccount) -> bool {
+    key.starts_with(&format!("{}/", account.name))
+}
+
+fn print_summary(outcomes: &[RecordOutcome]) {
+    let rows: Vec<Vec<String>> = outcomes
+        .iter()
+        .map(|outcome| {
+            vec![
+                outcome.account.clone(),
+                outcome.name.clone(),
+                outcome.record_type.to_string(),
+                match (&outcome.status, &outcome.drift) {
+                    (RecordStatus::Updated, Some(live)) => format!("updated, was {}", live),
+                    (RecordStatus::Updated, None) => "updated".to_string(),
+                    (RecordStatus::InSync, _) => "in sync".to_string(),
+                    (RecordStatus::Failed(err), _) => format!("failed: {}", err),
+                },
+            ]
+        })
+        .collect();
+    println!();
+    print_table(&["ACCOUNT", "RECORD", "TYPE", "STATUS"], &rows);
+}
+
+/// A record dns-cli tracks, with the account and zone it is in
+#[derive(Serialize)]
+pub struct TrackedRecord {
+    account: String,
+    zone: String,
+    #[serde(flatten)]
+    record: Record,
+}
+
+impl Output for Vec<TrackedRecord> {
+    fn print_table(&self) {
+        let rows: Vec<Vec<String>> = self
+            .iter()
+            .map(|tracked| {
+                let record = &tracked.record;
+                vec![
+                    tracked.account.clone(),
+                    record.name.clone(),
+                    record.record_type.to_string(),
+                    record
+                        .content
+                        .as_ref()
+                        .map(|content| content.rdata(record.record_type))
+                        .unwrap_or_default(),
+                    match record.settings == RecordSettings::default() {
+                        true => "".to_string(),
+                        false => record.settings.to_string(),
+                    },
+                ]
+            })
+            .collect();
+        print_table(&["ACCOUNT", "NAME", "TYPE", "CONTENT", "SETTINGS"], &rows);
+    }
+}
+
+/// A record at the provider, with the account and zone it is in
+#[derive(Serialize)]
+pub struct RemoteRecord {
+    account: String,
+    zone: String,
+    /// Whether dns-cli tracks the record
+    tracked: bool,
+    #[serde(flatten)]
+    record: DomainDetails,
+}
+
+impl Output for Vec<RemoteRecord> {
+    /// A table per zone, the records dns-cli tracks are marked with a `*`
+    fn print_table(&self) {
+        for (index, remote) in self.iter().enumerate() {
+            let zone = (&remote.account, &remote.zone);
+            if index > 0 && (&self[index - 1].account, &self[index - 1].zone) == zone {
+                continue;
+            }
+            let rows: Vec<Vec<String>> = self[index..]
+                .iter()
+                .take_while(|r| (&r.account, &r.zone) == zone)
+                .map(|remote| {
+                    let record = &remote.record;
+                    vec![
+                        if remote.tracked { "*" } else { "" }.to_string(),
+                        record.name.clone(),
+                        record.record_type.clone(),
+                        record.content.clone(),
+                        match record.ttl {
+                            1 => "auto".to_string(),
+                            ttl => ttl.to_string(),
+                        },
+                        if record.proxied { "yes" } else { "no" }.to_string(),
+                        record.modified_on.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            println!("{} ({}):", remote.zone, remote.account);
+            print_table(
+                &["", "NAME", "TYPE", "CONTENT", "TTL", "PROXIED", "MODIFIED"],
+                &rows,
+            );
+            println!();
+        }
+    }
+}
+
+impl Output for DomainDetails {
+    fn print_table(&self) {
+        println!("Domain Details:");
+        println!("  Name: {}", self.name);
+        println!("  Type: {}", self.record_type);
+        println!("  Content: {}", self.content);
+        println!("  Proxied: {}", self.proxied);
+        println!("  TTL: {}", self.ttl);
+        println!(
+            "  Last Updated: {}",
+            self.modified_on.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Filters for listing the records at the provider
+#[derive(Debug, Default)]
+pub struct RecordFilter {
+    /// The record type, compared without case
+    pub record_type: Option<String>,
+    /// A glob for the record name where `*` matches any text and `?` a single character
+    pub name: Option<String>,
+    /// Text the content has to contain
+    pub content: Option<String>,
+}
+
+impl RecordFilter {
+    fn matches(&self, record: &DomainDetails) -> bool {
+        self.record_type
+            .as_ref()
+            .is_none_or(|t| t.eq_ignore_ascii_case(&record.record_type))
+            && self.name.as_ref().is_none_or(|pattern| {
+                glob_match(&pattern.to_lowercase(), &record.name.to_lowercase())
+            })
+            && self
+                .content
+                .as_ref()
+                .is_none_or(|content| record.content.contains(content.as_str()))
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Where to continue after the last `*` when the text stops matching
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The changes that make a zone of an account match a desired-state file
+struct ZonePlan {
+    api_index: usize,
+    zone_id: String,
+    zone: String,
+    diff: ZoneDiff,
+}
+
+/// A change of a plan or an import
+#[derive(Serialize)]
+struct ChangeSummary {
+    /// `create`, `update` or `delete`
+    action: &'static str,
+    name: String,
+    record_type: String,
+    /// The content the record gets, or the content of the deleted record
+    content: String,
+    /// The content before an update that changes it
+    previous_content: Option<String>,
+    /// The change as it is shown to people
+    #[serde(skip)]
+    text: String,
+}
+
+impl From<&Change> for ChangeSummary {
+    fn from(change: &Change) -> ChangeSummary {
+        let text = change.to_string();
+        match change {
+            Change::Create(desired) => ChangeSummary {
+                action: "create",
+                name: desired.record.name.clone(),
+                record_type: desired.record.record_type.to_string(),
+                content: desired.value.clone(),
+                previous_content: None,
+                text,
+            },
+            Change::Update { live, desired } => ChangeSummary {
+                action: "update",
+                name: desired.record.name.clone(),
+                record_type: desired.record.record_type.to_string(),
+                content: desired.value.clone(),
+                previous_content: (!same_value(
+                    desired.record.record_type,
+                    &live.content,
+                    &desired.value,
+                ))
+                .then(|| live.content.clone()),
+                text,
+            },
+            Change::Delete(live) => ChangeSummary {
+                action: "delete",
+                name: live.name.clone(),
+                record_type: live.record_type.clone(),
+                content: live.content.clone(),
+                previous_content: None,
+                text,
+            },
+        }
+    }
+}
+
+/// The changes of a zone of an account
+#[derive(Serialize)]
+struct ZoneChanges {
+    zone: String,
+    account: String,
+    changes: Vec<ChangeSummary>,
+    /// Live records that are not in the file and are left alone without `--prune`
+    undeclared: usize,
+}
+
+/// What `plan` found and `apply` did
+#[derive(Serialize)]
+pub struct PlanReport {
+    zones: Vec<ZoneChanges>,
+    /// The number of changes `apply` made, None for `plan` and dry runs
+    applied: Option<usize>,
+    #[serde(skip)]
+    prune: bool,
+}
+
+impl PlanReport {
+    fn new(plans: &[ZonePlan], account_names: &[String], prune: bool) -> PlanReport {
+        let zones = plans
+            .iter()
+            .map(|plan| ZoneChanges {
+                zone: plan.zone.clone(),
+                account: account_names[plan.api_index].clone(),
+                changes: plan.diff.changes.iter().map(ChangeSummary::from).collect(),
+                undeclared: plan.diff.undeclared,
+            })
+            .collect();
+        PlanReport {
+            zones,
+            applied: None,
+            prune,
+        }
+    }
+
+    fn has_changes(&self) -> bool {
+        self.zones.iter().any(|zone| !zone.changes.is_empty())
+    }
+
+    /// The changes of every zone and how many there are of each kind
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for zone in &self.zones {
+            lines.push(format!("{} ({}):", zone.zone, zone.account));
+            if zone.changes.is_empty() {
+                lines.push("  no changes".to_string());
+            }
+            lines.extend(
+                zone.changes
+                    .iter()
+                    .map(|change| format!("  {}", change.text)),
+            );
+        }
+        let count = |action: &str| {
+            self.zones
+                .iter()
+                .flat_map(|zone| zone.changes.iter())
+                .filter(|change| change.action == action)
+                .count()
+        };
+        lines.push("".to_string());
+        lines.push(format!(
+            "Plan: {} to create, {} to update, {} to delete.",
+            count("create"),
+            count("update"),
+            count("delete")
+        ));
+        let undeclared: usize = self.zones.iter().map(|zone| zone.undeclared).sum();
+        if undeclared > 0 && !self.prune {
+            lines.push(format!(
+                "{} record(s) at the providers are not in the file, use --prune to delete them.",
+                undeclared
+            ));
+        }
+        lines
+    }
+}
+
+impl Output for PlanReport {
+    fn print_table(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// What `zone import` found in a zone file and did
+#[derive(Serialize)]
+pub struct ImportReport {
+    zone: String,
+    account: String,
+    create: Vec<ChangeSummary>,
+    /// Records of the file that are already in the zone with the same content
+    existing: usize,
+    /// Records of types dns-cli does not manage, like NS and SOA
+    skipped: Vec<String>,
+    /// The number of records created, None when nothing was created
+    imported: Option<usize>,
+}
+
+impl ImportReport {
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} ({}):", self.zone, self.account)];
+        lines.extend(
+            self.create
+                .iter()
+                .map(|change| format!("  {}", change.text)),
+        );
+        lines.push("".to_string());
+        lines.push(format!(
+            "Import: {} to create, {} already in the zone.",
+            self.create.len(),
+            self.existing
+        ));
+        if !self.skipped.is_empty() {
+            lines.push(format!(
+                "Skipped records dns-cli does not manage: {}",
+                self.skipped.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+impl Output for ImportReport {
+    fn print_table(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// GoDaddy replaces all records of a name and type at once, so a zone there can only get
+/// one of each
+fn check_one_per_name_and_type(zone: &str, records: &[PlannedRecord]) -> Result<(), DnsCliError> {
+    let mut keys = HashSet::new();
+    for record in records {
+        let key = (
+            record.record.name.to_lowercase(),
+            record.record.record_type.to_string(),
+        );
+        if !keys.insert(key) {
+            return Err(DnsCliError::Config(format!(
+                "GoDaddy zone {} can only have one {} record named {}",
+                zone, record.record.record_type, record.record.name
+            )));
+        }
+    }
+    Ok(())
+}
 
 pub struct CLIProgram<T>
 where
@@ -12,210 +516,1110 @@ where
     debug: bool,
     dry_run: bool,
     config: Config,
-    api: T,
+    apis: Vec<T>,
 }
 
 impl<T> CLIProgram<T>
 where
     T: DnsProvider,
 {
-    pub fn new(api: T, debug: bool, dry_run: bool, config: Config) -> CLIProgram<T> {
+    /// Creates a program that works on the accounts of all the given providers
+    pub fn new(apis: Vec<T>, debug: bool, dry_run: bool, config: Config) -> CLIProgram<T> {
         CLIProgram {
             debug,
             dry_run,
             config,
-            api,
+            apis,
         }
     }
 
-    pub(crate) async fn check_for_new_ip(&self, force: bool) {
-        println!("Checking for new ip...");
-        let old_ip_opt = get_last_ip(self.debug);
-        let current_ip = get_current_ip().await.expect("Could not get current IP");
-        if old_ip_opt.is_none() {
-            println!("No previous IP found, saving current IP");
+    /// With `reconcile` set the live content of every tracked record is compared with the IP and
+    /// only records that drifted are updated, even when the IP has not changed
+    pub(crate) async fn check_for_new_ip(
+        &self,
+        force: bool,
+        reconcile: bool,
+    ) -> Result<CheckReport, DnsCliError> {
+        status!("Checking for new ip...");
+        let (state_path, legacy) = {
+            let singleton = CONFIG_SINGLETON.lock().await;
+            (singleton.state_path(), singleton.takes_legacy_state())
+        };
+        if self.debug {
+            status!("Using state file {:?}", state_path);
+        }
+        let mut state = State::load(&state_path, legacy)?;
+        let mut report = CheckReport {
+            ips: vec![],
+            records: vec![],
+            failures: vec![],
+        };
+        for version in self.tracked_ip_versions() {
+            let result = self
+                .check_ip_version(&mut state, version, force, reconcile)
+                .await;
             if !self.dry_run {
-                save_ip(&current_ip).await;
-            } else {
-                println!("[DRY RUN] Would save IP: {}", current_ip);
+                state.last_check = Some(Utc::now());
+                state.save(&state_path)?;
             }
-            return;
+            if let Err(err) = &result {
+                status!("Could not check {}: {}", version, err);
+            }
+            report.add(version, result);
         }
-        let old_ip = old_ip_opt.unwrap();
-        if old_ip == current_ip && !force {
-            println!("IP has not changed, doing nothing");
+        Ok(report)
+    }
+
+    /// The IP versions that have at least one tracked record, IPv4 if nothing is tracked yet
+    fn tracked_ip_versions(&self) -> Vec<IpVersion> {
+        let versions: Vec<IpVersion> = IpVersion::ALL
+            .into_iter()
+            .filter(|version| {
+                self.apis
+                    .iter()
+                    .flat_map(|api| api.account().domains.values())
+                    .flat_map(|domain| domain.records.iter())
+                    .any(|record| record.record_type.ip_version() == Some(*version))
+            })
+            .collect();
+        if versions.is_empty() {
+            vec![IpVersion::V4]
+        } else {
+            versions
+        }
+    }
+
+    async fn check_ip_version(
+        &self,
+        state: &mut State,
+        version: IpVersion,
+        force: bool,
+        reconcile: bool,
+    ) -> Result<(IpCheck, Vec<RecordOutcome>), DnsCliError> {
+        let current_ip = match get_current_ip(version).await {
+            Ok(ip) => ip,
+            Err(err) => {
+                if !self.dry_run {
+                    let error = match &err {
+                        DnsCliError::IpDetection(message) => message.clone(),
+                        err => err.to_string(),
+                    };
+                    self.notify(&NotifyEvent::IpDetectionFailed { version, error })
+                        .await;
+                }
+                return Err(err);
+            }
+        };
+        let previous_ip = state.ip(version).ip.clone();
+        let ip_check = IpCheck {
+            version,
+            ip: current_ip.clone(),
+            changed: previous_ip.as_ref().is_some_and(|ip| *ip != current_ip),
+            previous_ip: previous_ip.clone(),
+        };
+        let Some(old_ip) = previous_ip else {
+            status!("No previous {} found, saving current {}", version, version);
+            if !self.dry_run {
+                let ip_state = state.ip_mut(version);
+                ip_state.ip = Some(current_ip);
+                ip_state.last_change = Some(Utc::now());
+            } else {
+                status!("[DRY RUN] Would save {}: {}", version, current_ip);
+            }
+            return Ok((ip_check, vec![]));
+        };
+        let failed_records = state.ip(version).pending_failures.clone();
+        let retry_only = if old_ip == current_ip && reconcile {
+            status!(
+                "{} has not changed, comparing records with the provider...",
+                version
+            );
+            None
+        } else if old_ip == current_ip && !force {
+            if failed_records.is_empty() {
+                status!("{} has not changed, doing nothing", version);
+                return Ok((ip_check, vec![]));
+            }
+            status!(
+                "{} has not changed, retrying {} record(s) that failed last time...",
+                version,
+                failed_records.len()
+            );
+            Some(failed_records.as_slice())
         } else {
             if force {
-                println!("IP has not changed but force flag set, updating records...")
+                status!(
+                    "{} has not changed but force flag set, updating records...",
+                    version
+                )
             } else {
-                println!(
-                    "IP has changed to {} from {}, updating records...",
-                    current_ip, old_ip
+                status!(
+                    "{} has changed to {} from {}, updating records...",
+                    version,
+                    current_ip,
+                    old_ip
                 );
             }
+            None
+        };
 
-            if self.dry_run {
-                println!(
-                    "[DRY RUN] Would notify webhooks: {:?}",
-                    self.config.webhooks
-                );
-                println!(
-                    "[DRY RUN] Would update DNS records with new IP: {}",
-                    current_ip
-                );
-            } else {
-                println!("Notifying webhooks: {:?}", self.config.webhooks);
-                for webhook in &self.config.webhooks {
-                    match webhook {
-                        DiscordWebhook(webhook) => {
-                            webhook.change_ip(&old_ip, &current_ip).await;
-                        }
-                    }
+        if self.dry_run {
+            if old_ip != current_ip || force {
+                status!("[DRY RUN] Would notify the webhooks subscribed to ip_changed");
+            }
+            self.update_records(&current_ip, version.record_type(), retry_only, reconcile)
+                .await;
+            return Ok((ip_check, vec![]));
+        }
+
+        let outcomes = self
+            .update_records(&current_ip, version.record_type(), retry_only, reconcile)
+            .await;
+
+        // Failures of accounts that were not part of this check are kept for their next check
+        let mut still_failing: Vec<String> = failed_records
+            .into_iter()
+            .filter(|key| !self.apis.iter().any(|api| is_key_of(key, api.account())))
+            .collect();
+        let now = Utc::now();
+        for outcome in &outcomes {
+            match outcome.status {
+                RecordStatus::Failed(_) => still_failing.push(outcome.key.clone()),
+                RecordStatus::InSync => {}
+                RecordStatus::Updated => {
+                    state.records.insert(
+                        outcome.key.clone(),
+                        RecordState {
+                            last_success: now,
+                            ip: current_ip.clone(),
+                        },
+                    );
                 }
-                self.update_records(&current_ip).await;
-                save_ip(&current_ip).await;
             }
         }
+        let ip_state = state.ip_mut(version);
+        if old_ip != current_ip {
+            ip_state.last_change = Some(now);
+        }
+        ip_state.ip = Some(current_ip.clone());
+        ip_state.pending_failures = still_failing;
+
+        for outcome in &outcomes {
+            if let Some(live) = &outcome.drift {
+                self.notify(&NotifyEvent::DriftDetected {
+                    name: outcome.name.clone(),
+                    live: live.clone(),
+                    expected: current_ip.clone(),
+                })
+                .await;
+            }
+            match &outcome.status {
+                RecordStatus::Updated => {
+                    self.notify(&NotifyEvent::RecordUpdated {
+                        name: outcome.name.clone(),
+                        record_type: outcome.record_type,
+                        ip: current_ip.clone(),
+                    })
+                    .await
+                }
+                RecordStatus::Failed(err) => {
+                    self.notify(&NotifyEvent::RecordFailed {
+                        name: outcome.name.clone(),
+                        record_type: outcome.record_type,
+                        error: err.to_string(),
+                    })
+                    .await
+                }
+                RecordStatus::InSync => {}
+            }
+        }
+        if old_ip != current_ip || force {
+            let names = |failed: bool| {
+                outcomes
+                    .iter()
+                    .filter(|outcome| outcome.failed() == failed)
+                    .map(|outcome| outcome.name.clone())
+                    .collect()
+            };
+            let change = IpChange {
+                old_ip,
+                new_ip: current_ip,
+                records_updated: names(false),
+                failures: names(true),
+            };
+            self.notify(&NotifyEvent::IpChanged(change)).await;
+        }
+        Ok((ip_check, outcomes))
     }
 
-    pub async fn remove_sub_domain(&mut self, domain: String) {
-        let domain_chunks = domain.split('.').collect::<Vec<&str>>();
-        let zone_name = format!("{}.{}", domain_chunks[1], domain_chunks[2]); // TODO:
-                                                                              // Support for more than 3 chunks
-        println!("Removing subdomain {} for {}", domain_chunks[0], zone_name);
-        let zone = self
-            .config
-            .cloudflare_config
-            .domains
+    /// Sends the event to the webhooks subscribed to its kind
+    async fn notify(&self, event: &NotifyEvent) {
+        for webhook in self.config.webhooks.iter().filter(|w| w.wants(event)) {
+            status!("Notifying {} of {}", webhook.redacted().url(), event.kind());
+            webhook.notify(event).await;
+        }
+    }
+
+    /// Finds the provider and zone id a record name belongs to, the longest matching zone
+    /// name over all accounts wins
+    fn find_zone(&self, name: &str) -> Option<(usize, String)> {
+        self.apis
             .iter()
-            .find(|e| e.1.domain == zone_name)
-            .expect("Domain not found");
-        let record_index = zone
-            .1
-            .records
+            .enumerate()
+            .filter_map(|(index, api)| {
+                let domains = &api.account().domains;
+                find_zone_id(domains, name).map(|zone_id| {
+                    let zone_len = domains[&zone_id].domain.len();
+                    (index, zone_id, zone_len)
+                })
+            })
+            .max_by_key(|(_, _, zone_len)| *zone_len)
+            .map(|(index, zone_id, _)| (index, zone_id))
+    }
+
+    /// Finds a tracked record by its name, and its type when several records have the name.
+    /// Returns the index of the provider, the zone id and the index of the record in the zone.
+    fn find_record(
+        &self,
+        name: &str,
+        record_type: Option<RecordType>,
+    ) -> Result<(usize, String, usize), DnsCliError> {
+        let (api_index, zone_id) = self
+            .find_zone(name)
+            .ok_or_else(|| DnsCliError::Config(format!("No configured zone matches {}", name)))?;
+        let records = &self.apis[api_index].account().domains[&zone_id].records;
+        let matching: Vec<usize> = records
             .iter()
-            .position(|e| e.name == domain)
-            .expect("Record not found, maybe you want to run `import` first?");
-        let record = zone.1.records.get(record_index).unwrap();
+            .enumerate()
+            .filter(|(_, r)| {
+                r.name == name && (record_type.is_none() || record_type == Some(r.record_type))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        match matching.as_slice() {
+            [index] => Ok((api_index, zone_id, *index)),
+            [] => Err(DnsCliError::Config(format!(
+                "Record {} not found, maybe you want to run `import` first?",
+                name
+            ))),
+            _ => Err(DnsCliError::Config(format!(
+                "Several records are named {}, pick one with --type",
+                name
+            ))),
+        }
+    }
+
+    pub async fn remove_sub_domain(
+        &mut self,
+        domain: String,
+        record_type: Option<RecordType>,
+    ) -> Result<(), DnsCliError> {
+        let (api_index, zone_id, record_index) = self.find_record(&domain, record_type)?;
+        let api = &self.apis[api_index];
+        let zone = &api.account().domains[&zone_id];
+        status!(
+            "Removing record {} from {} ({})",
+            domain,
+            zone.domain,
+            api.account().name
+        );
+        let record = zone.records.get(record_index).unwrap();
 
         if self.dry_run {
-            println!("[DRY RUN] Would remove subdomain: {}", record.name);
-            println!("[DRY RUN] Would update configuration to stop tracking this subdomain");
-            return;
+            status!(
+                "[DRY RUN] Would remove {} record: {}",
+                record.record_type,
+                record.name
+            );
+            status!("[DRY RUN] Would update configuration to stop tracking this subdomain");
+            return Ok(());
         }
 
-        self.api.remove_sub_domain(record, zone.0.to_owned()).await;
-        let domains = self.config.cloudflare_config.domains.clone();
-
-        let key = domains.keys().next().unwrap();
-        self.config
-            .cloudflare_config
+        api.remove_sub_domain(record, zone_id.clone()).await?;
+        let api = &mut self.apis[api_index];
+        api.account_mut()
             .domains
-            .get_mut(key)
+            .get_mut(&zone_id)
             .unwrap()
             .records
-            .swap_remove(record_index);
-        CONFIG_SINGLETON.lock().await.save(self.config.clone())
+            .remove(record_index);
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(api.account().clone())
     }
 
-    async fn update_records(&self, new_ip: &str) {
-        println!("Updating records...");
+    /// Changes the content of a tracked record that does not point to the IP, `change` gets
+    /// the current content and returns the new one
+    pub async fn update_record_content<F>(
+        &mut self,
+        domain: String,
+        record_type: Option<RecordType>,
+        change: F,
+    ) -> Result<(), DnsCliError>
+    where
+        F: FnOnce(RecordContent) -> RecordContent,
+    {
+        let (api_index, zone_id, record_index) = self.find_record(&domain, record_type)?;
+        let api = &self.apis[api_index];
+        let mut record = api.account().domains[&zone_id].records[record_index].clone();
+        let Some(content) = record.content.take() else {
+            return Err(DnsCliError::Config(format!(
+                "{} is an {} record, it is updated by `check` when the IP changes",
+                record.name, record.record_type
+            )));
+        };
+        let content = change(content);
+        validate_record(record.record_type, &record.name, Some(&content))?;
+        record.content = Some(content);
+
         if self.dry_run {
-            println!("[DRY RUN] Would update DNS records with new IP: {}", new_ip);
-        } else {
-            self.api.change_ip(new_ip).await;
+            status!(
+                "[DRY RUN] Would update {} record {} to {}",
+                record.record_type,
+                record.name,
+                record.display_content("")
+            );
+            return Ok(());
         }
+
+        api.update_record(&record, &zone_id, "").await?;
+        let api = &mut self.apis[api_index];
+        api.account_mut().domains.get_mut(&zone_id).unwrap().records[record_index] = record;
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(api.account().clone())
     }
 
-    pub(crate) async fn register_sub_domain(&mut self, domain: String) {
-        let domain_chunks = domain.split('.').collect::<Vec<&str>>();
-        let zone_name = format!("{}.{}", domain_chunks[1], domain_chunks[2]); // TODO:
-                                                                              // Support for more than 3 chunks
-        println!(
-            "Registering subdomain {} for {}",
-            domain_chunks[0], zone_name
-        );
+    /// Updates the tracked records of a type one by one, a failed record does not stop the
+    /// others. With `only` set just the records with those keys are updated. With `reconcile`
+    /// set records whose live content already is the IP are left alone.
+    async fn update_records(
+        &self,
+        new_ip: &str,
+        record_type: RecordType,
+        only: Option<&[String]>,
+        reconcile: bool,
+    ) -> Vec<RecordOutcome> {
+        status!("Updating records...");
+        let mut outcomes = vec![];
+        for api in &self.apis {
+            let account = api.account();
+            for (zone_id, domain) in account.domains.iter() {
+                for record in domain.records.iter() {
+                    let key = record_key(account, record);
+                    if record.record_type != record_type
+                        || only.is_some_and(|only| !only.contains(&key))
+                    {
+                        continue;
+                    }
+                    if self.dry_run {
+                        if reconcile {
+                            status!(
+                                "[DRY RUN] Would update {} to {} if its live content differs",
+                                record.name,
+                                new_ip
+                            );
+                        } else {
+                            status!("[DRY RUN] Would update {} to {}", record.name, new_ip);
+                        }
+                        continue;
+                    }
+                    let (status, drift) = self
+                        .update_record(api, record, zone_id, new_ip, reconcile)
+                        .await;
+                    if let RecordStatus::Failed(err) = &status {
+                        status!("Failed to update {}: {}", record.name, err);
+                    }
+                    outcomes.push(RecordOutcome {
+                        key,
+                        account: account.name.clone(),
+                        name: record.name.clone(),
+                        record_type,
+                        status,
+                        drift,
+                    });
+                }
+            }
+        }
+        outcomes
+    }
 
-        let zone_id = self
-            .config
-            .cloudflare_config
-            .domains
-            .iter()
-            .find(|e| e.1.domain == zone_name)
-            .expect("Domain not found");
+    async fn update_record(
+        &self,
+        api: &T,
+        record: &Record,
+        zone_id: &str,
+        new_ip: &str,
+        reconcile: bool,
+    ) -> (RecordStatus, Option<String>) {
+        let mut drift = None;
+        if reconcile {
+            match api.get_record_content(record, zone_id).await {
+                Ok(live) if live == new_ip => return (RecordStatus::InSync, None),
+                Ok(live) => {
+                    status!(
+                        "{} drifted, it points to {} instead of {}",
+                        record.name,
+                        live,
+                        new_ip
+                    );
+                    drift = Some(live);
+                }
+                Err(err) => return (RecordStatus::Failed(err), None),
+            }
+        }
+        match api.update_record(record, zone_id, new_ip).await {
+            Ok(()) => (RecordStatus::Updated, drift),
+            Err(err) => (RecordStatus::Failed(err), drift),
+        }
+    }
+
+    /// Creates a record and starts tracking it. A and AAAA records point to the current IP,
+    /// other types need their content, which is validated before anything is sent. The
+    /// settings are kept in the config and sent again on every update.
+    pub(crate) async fn register_sub_domain(
+        &mut self,
+        domain: String,
+        record_type: RecordType,
+        content: Option<RecordContent>,
+        settings: RecordSettings,
+    ) -> Result<(), DnsCliError> {
+        validate_record(record_type, &domain, content.as_ref())?;
+        settings.validate(record_type, &domain)?;
+        let (api_index, zone_id) = self
+            .find_zone(&domain)
+            .ok_or_else(|| DnsCliError::Config(format!("No configured zone matches {}", domain)))?;
+        let api = &self.apis[api_index];
+        let zone_name = api.account().domains[&zone_id].domain.clone();
+        status!(
+            "Registering {} record {} for {} ({})",
+            record_type,
+            domain,
+            zone_name,
+            api.account().name
+        );
 
         if self.dry_run {
-            println!(
-                "[DRY RUN] Would create subdomain {} for zone {}",
-                domain, zone_name
+            status!(
+                "[DRY RUN] Would create {} record {} for zone {}",
+                record_type,
+                domain,
+                zone_name
             );
-            println!("[DRY RUN] Would update configuration to track the new subdomain");
-            return;
+            if let Some(content) = &content {
+                status!("[DRY RUN] With content: {}", content.rdata(record_type));
+            }
+            if settings != RecordSettings::default() {
+                status!("[DRY RUN] With settings: {}", settings);
+            }
+            status!("[DRY RUN] Would update configuration to track the new subdomain");
+            return Ok(());
         }
 
-        let id = self
-            .api
-            .set_sub_domain(
-                &Record {
-                    id: "".to_string(),
-                    name: domain.to_string(),
-                    record_type: RecordType::A,
-                },
-                zone_id.0.to_owned(),
-            )
-            .await;
-        let domains = self.config.cloudflare_config.domains.clone();
-
-        let key = domains.keys().next().unwrap();
-        self.config
-            .cloudflare_config
+        let mut record = Record {
+            id: "".to_string(),
+            name: domain.to_string(),
+            record_type,
+            content,
+            settings,
+        };
+        record.id = api.set_sub_domain(&record, zone_id.clone()).await?;
+        let api = &mut self.apis[api_index];
+        api.account_mut()
             .domains
-            .get_mut(key)
+            .get_mut(&zone_id)
             .unwrap()
             .records
-            .push(Record {
-                id,
-                name: domain.to_owned(),
-                record_type: RecordType::A,
+            .push(record);
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(api.account().clone())
+    }
+
+    /// Finds the provider and id of a zone by its name, in the given account or else the
+    /// first account that has it
+    fn find_zone_by_name(
+        &self,
+        zone: &str,
+        account: Option<&str>,
+    ) -> Result<(usize, String), DnsCliError> {
+        let zone = zone.trim_end_matches('.');
+        self.apis
+            .iter()
+            .enumerate()
+            .filter(|(_, api)| account.is_none_or(|name| api.account().name == name))
+            .find_map(|(index, api)| {
+                api.account()
+                    .domains
+                    .iter()
+                    .find(|(_, domain)| {
+                        domain
+                            .domain
+                            .trim_end_matches('.')
+                            .eq_ignore_ascii_case(zone)
+                    })
+                    .map(|(zone_id, _)| (index, zone_id.clone()))
+            })
+            .ok_or_else(|| match account {
+                Some(account) => DnsCliError::Config(format!(
+                    "Zone {} is not configured in account {}",
+                    zone, account
+                )),
+                None => DnsCliError::Config(format!("Zone {} is not configured", zone)),
+            })
+    }
+
+    /// Checks every record of a desired-state file and compares the zones with the records at
+    /// the providers. Nothing is sent to a provider before the whole file is valid.
+    async fn plan_zones(
+        &self,
+        state: &DesiredState,
+        prune: bool,
+    ) -> Result<Vec<ZonePlan>, DnsCliError> {
+        let mut ips = HashMap::new();
+        for version in IpVersion::ALL {
+            let needed = state
+                .zones
+                .iter()
+                .flat_map(|zone| zone.records.iter())
+                .any(|r| r.content.is_none() && r.record_type.ip_version() == Some(version));
+            if needed {
+                ips.insert(version, get_current_ip(version).await?);
+            }
+        }
+
+        let mut planned = vec![];
+        let mut seen = HashSet::new();
+        for zone in &state.zones {
+            let (api_index, zone_id) =
+                self.find_zone_by_name(&zone.zone, zone.account.as_deref())?;
+            if !seen.insert((api_index, zone_id.clone())) {
+                return Err(DnsCliError::Config(format!(
+                    "Zone {} is declared more than once",
+                    zone.zone
+                )));
+            }
+            let records = zone
+                .records
+                .iter()
+                .map(|record| record.plan(&zone.zone, &ips))
+                .collect::<Result<Vec<_>, _>>()?;
+            for record in &records {
+                self.apis[api_index].check_record(&record.record, &zone_id)?;
+            }
+            if self.apis[api_index].account().kind() == ProviderKind::GoDaddy {
+                check_one_per_name_and_type(&zone.zone, &records)?;
+            }
+            planned.push((api_index, zone_id, zone.zone.clone(), records));
+        }
+
+        let mut plans = vec![];
+        for (api_index, zone_id, zone, records) in planned {
+            let live = self.apis[api_index].list_records(&zone_id).await?;
+            plans.push(ZonePlan {
+                api_index,
+                zone_id,
+                zone,
+                diff: diff(records, live, prune),
             });
-        CONFIG_SINGLETON.lock().await.save(self.config.clone())
+        }
+        Ok(plans)
+    }
+
+    /// What `apply` would change to make the zones match a desired-state file
+    pub async fn plan(&self, state: &DesiredState, prune: bool) -> Result<PlanReport, DnsCliError> {
+        let plans = self.plan_zones(state, prune).await?;
+        Ok(PlanReport::new(&plans, &self.account_names(), prune))
+    }
+
+    /// Creates, updates and, with `prune` set, deletes records until the zones match a
+    /// desired-state file. A and AAAA records without content are tracked so `check` keeps
+    /// them pointed at this server. Stops at the first change that fails. The plan is shown
+    /// as progress and confirmed before anything is changed.
+    pub async fn apply(
+        &mut self,
+        state: &DesiredState,
+        prune: bool,
+    ) -> Result<PlanReport, DnsCliError> {
+        let plans = self.plan_zones(state, prune).await?;
+        let mut report = PlanReport::new(&plans, &self.account_names(), prune);
+        for line in report.lines() {
+            status!("{}", line);
+        }
+        if self.dry_run {
+            if report.has_changes() {
+                status!("[DRY RUN] Would apply the changes above");
+            }
+            return Ok(report);
+        }
+        if report.has_changes() && !interaction::proceed("Apply the changes above?")? {
+            status!("Nothing applied");
+            report.applied = Some(0);
+            return Ok(report);
+        }
+
+        let mut applied = 0;
+        for plan in plans {
+            let mut synced = plan.diff.in_sync;
+            let mut removed = vec![];
+            let mut result = Ok(());
+            let api = &self.apis[plan.api_index];
+            for change in plan.diff.changes {
+                let outcome = match change {
+                    Change::Create(mut desired) => api
+                        .create_record(&desired.record, &plan.zone_id, &desired.value)
+                        .await
+                        .map(|id| {
+                            desired.record.id = id;
+                            synced.push(desired);
+                        }),
+                    Change::Update { live, mut desired } => {
+                        desired.record.id = live.id;
+                        api.update_record(&desired.record, &plan.zone_id, &desired.value)
+                            .await
+                            .map(|_| synced.push(desired))
+                    }
+                    Change::Delete(live) => match RecordType::from_api_name(&live.record_type) {
+                        Some(record_type) => {
+                            let record = Record {
+                                id: live.id.clone(),
+                                name: live.name,
+                                record_type,
+                                content: None,
+                                settings: RecordSettings::default(),
+                            };
+                            api.remove_sub_domain(&record, plan.zone_id.clone())
+                                .await
+                                .map(|_| removed.push(live.id))
+                        }
+                        None => Err(DnsCliError::Api(format!(
+                            "Can't delete {} record {} of type {}, the type is not managed",
+                            live.id, live.name, live.record_type
+                        ))),
+                    },
+                };
+                if let Err(err) = outcome {
+                    result = Err(err);
+                    break;
+                }
+                applied += 1;
+            }
+            self.track_applied(plan.api_index, &plan.zone_id, synced, &removed)
+                .await?;
+            result?;
+        }
+        if applied > 0 {
+            status!("Applied {} change(s)", applied);
+        }
+        report.applied = Some(applied);
+        Ok(report)
     }
 
-    pub fn ls(&self) {
-        for (_, domain) in &self.config.cloudflare_config.domains {
-            for record in &domain.records {
-                println!("{} - {}", record.name, record.record_type.to_string());
+    /// Keeps the tracked records of a zone in line with what was applied. Records pointing to
+    /// this server are tracked, tracked records that were deleted or now have a fixed IP are
+    /// no longer tracked.
+    async fn track_applied(
+        &mut self,
+        api_index: usize,
+        zone_id: &str,
+        synced: Vec<PlannedRecord>,
+        removed: &[String],
+    ) -> Result<(), DnsCliError> {
+        let api = &mut self.apis[api_index];
+        let records = &mut api.account_mut().domains.get_mut(zone_id).unwrap().records;
+        let before = records.len();
+        records.retain(|record| !removed.contains(&record.id));
+        let mut changed = records.len() != before;
+        for planned in synced {
+            let tracked = planned.dynamic || planned.record.content.is_some();
+            match records.iter().position(|r| r.id == planned.record.id) {
+                Some(index) if tracked => records[index] = planned.record,
+                Some(index) => {
+                    records.remove(index);
+                }
+                None if planned.dynamic => records.push(planned.record),
+                None => continue,
             }
+            changed = true;
+        }
+        if !changed {
+            return Ok(());
         }
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(api.account().clone())
     }
 
-    pub async fn import(&mut self) {
+    /// Writes the live records of a zone as a BIND zone file
+    pub async fn export_zone(
+        &self,
+        zone: &str,
+        account: Option<&str>,
+    ) -> Result<String, DnsCliError> {
+        let (api_index, zone_id) = self.find_zone_by_name(zone, account)?;
+        let records = self.apis[api_index].list_records(&zone_id).await?;
+        Ok(zone_file::export(zone, &records))
+    }
+
+    /// Creates the records of a BIND zone file that the zone does not have yet. Records that
+    /// exist with the same content are left alone, nothing is updated or deleted. The records
+    /// to create are shown as progress and confirmed first.
+    pub async fn import_zone(
+        &self,
+        text: &str,
+        zone: Option<&str>,
+        account: Option<&str>,
+    ) -> Result<ImportReport, DnsCliError> {
+        let file = zone_file::parse(text, zone)?;
+        let zone = match zone.map(str::to_string).or(file.origin) {
+            Some(zone) => zone.trim_end_matches('.').to_string(),
+            None => {
+                return Err(DnsCliError::Config(
+                    "The zone file has no $ORIGIN, give the zone with --zone".to_string(),
+                ))
+            }
+        };
+        let (api_index, zone_id) = self.find_zone_by_name(&zone, account)?;
+        let api = &self.apis[api_index];
+
+        let mut planned = vec![];
+        let mut skipped: Vec<String> = vec![];
+        for record in &file.records {
+            let name = record.name.to_lowercase();
+            if name != zone.to_lowercase() && !name.ends_with(&format!(".{}", zone.to_lowercase()))
+            {
+                return Err(DnsCliError::Config(format!(
+                    "Record {} on line {} is not in zone {}",
+                    record.name, record.line, zone
+                )));
+            }
+            match record.plan()? {
+                Some(record) => {
+                    api.check_record(&record.record, &zone_id)?;
+                    planned.push(record)
+                }
+                None => skipped.push(format!("{} {}", record.name, record.record_type)),
+            }
+        }
+
+        let live = api.list_records(&zone_id).await?;
+        let exists = |record: &PlannedRecord| {
+            live.iter().any(|l| {
+                l.name.eq_ignore_ascii_case(&record.record.name)
+                    && l.record_type == record.record.record_type.to_string()
+                    && same_value(record.record.record_type, &l.content, &record.value)
+            })
+        };
+        let mut existing = 0;
+        let mut to_create: Vec<PlannedRecord> = vec![];
+        for record in planned {
+            let duplicate = to_create.iter().any(|r| {
+                r.record.name.eq_ignore_ascii_case(&record.record.name)
+                    && r.record.record_type == record.record.record_type
+                    && same_value(r.record.record_type, &r.value, &record.value)
+            });
+            if exists(&record) || duplicate {
+                existing += 1;
+            } else {
+                to_create.push(record);
+            }
+        }
+        if api.account().kind() == ProviderKind::GoDaddy {
+            check_one_per_name_and_type(&zone, &to_create)?;
+            if let Some(record) = to_create.iter().find(|r| {
+                live.iter().any(|l| {
+                    l.name.eq_ignore_ascii_case(&r.record.name)
+                        && l.record_type == r.record.record_type.to_string()
+                })
+            }) {
+                return Err(DnsCliError::Config(format!(
+                    "GoDaddy zone {} already has a {} record named {}, importing would replace it",
+                    zone, record.record.record_type, record.record.name
+                )));
+            }
+        }
+
+        let mut report = ImportReport {
+            zone,
+            account: api.account().name.clone(),
+            create: to_create
+                .iter()
+                .map(|record| ChangeSummary::from(&Change::Create(record.clone())))
+                .collect(),
+            existing,
+            skipped,
+            imported: None,
+        };
+        for line in report.lines() {
+            status!("{}", line);
+        }
+        if to_create.is_empty() {
+            return Ok(report);
+        }
         if self.dry_run {
-            println!("[DRY RUN] Would import DNS records with matching IP");
-            return;
-        }
-        self.api.import().await;
-    }
-
-    pub async fn inspect_domain(&mut self, prefix: String) {
-        println!("Inspecting domain: {}", prefix);
-
-        match self.api.get_domain_details(&prefix).await {
-            Ok(details) => {
-                println!("Domain Details:");
-                println!("  Name: {}", details.name);
-                println!("  Type: {}", details.record_type);
-                println!("  Content: {}", details.content);
-                println!("  Proxied: {}", details.proxied);
-                println!("  TTL: {}", details.ttl);
-                println!(
-                    "  Last Updated: {}",
-                    details.modified_on.unwrap_or_default()
-                );
+            status!("[DRY RUN] Would create the records above");
+            return Ok(report);
+        }
+        if !interaction::proceed(&format!("Create {} record(s)?", to_create.len()))? {
+            status!("Nothing imported");
+            return Ok(report);
+        }
+        for record in &to_create {
+            api.create_record(&record.record, &zone_id, &record.value)
+                .await?;
+        }
+        status!("Imported {} record(s)", to_create.len());
+        report.imported = Some(to_create.len());
+        Ok(report)
+    }
+
+    fn account_names(&self) -> Vec<String> {
+        self.apis
+            .iter()
+            .map(|api| api.account().name.clone())
+            .collect()
+    }
+
+    /// The records dns-cli tracks in every account
+    pub fn ls(&self) -> Vec<TrackedRecord> {
+        let mut records = vec![];
+        for api in &self.apis {
+            let mut zones: Vec<_> = api.account().domains.values().collect();
+            zones.sort_by(|a, b| a.domain.cmp(&b.domain));
+            for domain in zones {
+                records.extend(domain.records.iter().map(|record| TrackedRecord {
+                    account: api.account().name.clone(),
+                    zone: domain.domain.clone(),
+                    record: record.clone(),
+                }));
             }
-            Err(e) => {
-                println!("Failed to fetch domain details: {}", e);
+        }
+        records
+    }
+
+    /// Lists the records of every configured zone as the provider has them and whether
+    /// dns-cli tracks them
+    pub async fn ls_remote(&self, filter: &RecordFilter) -> Result<Vec<RemoteRecord>, DnsCliError> {
+        let mut records = vec![];
+        for api in &self.apis {
+            let account = api.account();
+            let mut zones: Vec<_> = account.domains.iter().collect();
+            zones.sort_by(|a, b| a.1.domain.cmp(&b.1.domain));
+            for (zone_id, domain) in zones {
+                for record in api.list_records(zone_id).await? {
+                    if !filter.matches(&record) {
+                        continue;
+                    }
+                    records.push(RemoteRecord {
+                        account: account.name.clone(),
+                        zone: domain.domain.clone(),
+                        tracked: domain.records.iter().any(|r| r.id == record.id),
+                        record,
+                    });
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    pub async fn import(&mut self) -> Result<(), DnsCliError> {
+        if self.dry_run {
+            status!("[DRY RUN] Would import DNS records with matching IP");
+            return Ok(());
+        }
+        for api in self.apis.iter_mut() {
+            api.import().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn inspect_domain(&mut self, prefix: String) -> Result<DomainDetails, DnsCliError> {
+        status!("Inspecting domain: {}", prefix);
+
+        let mut result = Err(DnsCliError::Config(format!(
+            "No domain found with prefix: {}",
+            prefix
+        )));
+        for api in &self.apis {
+            result = api.get_domain_details(&prefix).await;
+            if result.is_ok() {
+                break;
             }
         }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        glob_match, ChangeSummary, CheckReport, IpCheck, PlanReport, RecordFilter, RecordOutcome,
+        RecordStatus, ZoneChanges,
+    };
+    use crate::config::RecordType;
+    use crate::desired_state::{Change, PlannedRecord};
+    use crate::dns_provider::DomainDetails;
+    use crate::error::DnsCliError;
+    use crate::ip_handler::IpVersion;
+    use crate::test_fixtures::{live, record};
+    use serde_json::json;
+
+    #[test]
+    fn glob_match_handles_stars_and_question_marks() {
+        assert!(glob_match("*.example.com", "a.b.example.com"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("db?.example.com", "db1.example.com"));
+        assert!(glob_match("*db*", "old-db-2.example.com"));
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(!glob_match("db?.example.com", "db12.example.com"));
+    }
+
+    #[test]
+    fn record_filter_combines_type_name_and_content() {
+        let record = live("VPN.example.com", "A", "192.0.2.1");
+        let filter = RecordFilter {
+            record_type: Some("a".to_string()),
+            name: Some("vpn.*".to_string()),
+            content: Some("192.0.2".to_string()),
+        };
+        assert!(filter.matches(&record));
+        let filter = RecordFilter {
+            record_type: Some("aaaa".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&record));
+    }
+
+    #[test]
+    fn check_report_has_stable_field_names() {
+        let outcome = |name: &str, status| RecordOutcome {
+            key: format!("main/{}", name),
+            account: "main".to_string(),
+            name: name.to_string(),
+            record_type: RecordType::A,
+            status,
+            drift: None,
+        };
+        let report = CheckReport {
+            ips: vec![IpCheck {
+                version: IpVersion::V4,
+                ip: "192.0.2.2".to_string(),
+                previous_ip: Some("192.0.2.1".to_string()),
+                changed: true,
+            }],
+            records: vec![
+                outcome("a.example.com", RecordStatus::Updated),
+                outcome(
+                    "b.example.com",
+                    RecordStatus::Failed(DnsCliError::Api("rate limited".to_string())),
+                ),
+            ],
+            failures: vec![],
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "ips": [{"version": "v4", "ip": "192.0.2.2", "previous_ip": "192.0.2.1", "changed": true}],
+                "records": [
+                    {"account": "main", "name": "a.example.com", "record_type": "A", "status": "updated", "drift": null},
+                    {"account": "main", "name": "b.example.com", "record_type": "A", "status": "failed", "error": "API error: rate limited", "drift": null},
+                ]
+            })
+        );
+        assert!(matches!(
+            report.result(),
+            Err(DnsCliError::RecordsFailed(1))
+        ));
+    }
+
+    #[test]
+    fn failed_ip_detection_does_not_drop_the_other_version() {
+        let mut report = CheckReport {
+            ips: vec![],
+            records: vec![],
+            failures: vec![],
+        };
+        report.add(
+            IpVersion::V4,
+            Err(DnsCliError::IpDetection("no source answered".to_string())),
+        );
+        report.add(
+            IpVersion::V6,
+            Ok((
+                IpCheck {
+                    version: IpVersion::V6,
+                    ip: "2001:db8::2".to_string(),
+                    previous_ip: Some("2001:db8::1".to_string()),
+                    changed: true,
+                },
+                vec![RecordOutcome {
+                    key: "main/1".to_string(),
+                    account: "main".to_string(),
+                    name: "www.example.com".to_string(),
+                    record_type: RecordType::AAAA,
+                    status: RecordStatus::Updated,
+                    drift: None,
+                }],
+            )),
+        );
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "ips": [{"version": "v6", "ip": "2001:db8::2", "previous_ip": "2001:db8::1", "changed": true}],
+                "records": [
+                    {"account": "main", "name": "www.example.com", "record_type": "AAAA", "status": "updated", "drift": null},
+                ],
+                "failures": [{"version": "v4", "error": "Could not detect IP: no source answered"}]
+            })
+        );
+        assert!(matches!(report.result(), Err(DnsCliError::IpDetection(_))));
+    }
+
+    #[test]
+    fn plan_report_has_the_changes_with_their_previous_content() {
+        let live = DomainDetails {
+            id: "1".to_string(),
+            ttl: 300,
+            ..live("www.example.com", "CNAME", "old.example.net")
+        };
+        let desired = PlannedRecord {
+            record: record("www.example.com", RecordType::CNAME),
+            value: "web.example.net".to_string(),
+            dynamic: false,
+        };
+        let report = PlanReport {
+            zones: vec![ZoneChanges {
+                zone: "example.com".to_string(),
+                account: "personal".to_string(),
+                changes: vec![ChangeSummary::from(&Change::Update { live, desired })],
+                undeclared: 2,
+            }],
+            applied: None,
+            prune: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["zones"][0]["changes"][0],
+            json!({
+                "action": "update",
+                "name": "www.example.com",
+                "record_type": "CNAME",
+                "content": "web.example.net",
+                "previous_content": "old.example.net"
+            })
+        );
+        assert_eq!(
+            report.lines(),
+            [
+                "example.com (personal):",
+                "  ~ www.example.com CNAME old.example.net -> web.example.net",
+                "",
+                "Plan: 0 to create, 1 to update, 0 to delete.",
+                "2 record(s) at the providers are not in the file, use --prune to delete them.",
+            ]
+        );
     }
 }
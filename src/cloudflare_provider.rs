@@ -1,13 +1,20 @@
-use inquire::{prompt_text, Confirm};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 const CLOUDFLARE_API_URL: &str = "https://api.cloudflare.com/client/v4";
 
-use crate::config::{Config, Domain, Record, CONFIG_SINGLETON};
-use crate::dns_provider::DnsProvider;
-use crate::ip_handler::get_current_ip;
+use crate::config::{
+    Domain, ProviderAccount, ProviderCredentials, Record, RecordSettings, RecordType,
+    CONFIG_SINGLETON,
+};
+use crate::dns_provider::{merge_imported, DnsProvider, DomainDetails};
+use crate::error::DnsCliError;
+use crate::interaction;
+use crate::ip_handler::{get_current_ip, IpVersion};
+use crate::output::status;
+use crate::secret::Secret;
 
-use serde_json::{from_str, json, Value};
+use serde_json::{json, Value};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ZoneResponse {
@@ -30,8 +37,20 @@ pub struct DNSListResponse {
     pub success: bool,
     pub errors: Vec<Value>,
     pub messages: Vec<Value>,
+    #[serde(rename = "result_info", default)]
+    pub result_info: Option<ResultInfo>,
 }
 
+/// Paging of list responses
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultInfo {
+    pub page: u32,
+    pub total_pages: u32,
+}
+
+/// Records fetched per request when listing a zone
+const PAGE_SIZE: u32 = 500;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DNSCreateResponse {
@@ -49,6 +68,9 @@ pub struct DnsRecord {
     #[serde(rename = "type")]
     pub type_field: String,
     pub content: String,
+    /// Set on MX and SRV records
+    #[serde(default)]
+    pub priority: Option<u16>,
     pub proxiable: bool,
     pub proxied: bool,
     pub ttl: i64,
@@ -64,7 +86,8 @@ pub struct DnsRecord {
 
 pub struct CloudflareProvider {
     client: Client,
-    config: Config,
+    account: ProviderAccount,
+    api_token: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -74,255 +97,336 @@ struct UpdateResponse {
     errors: Vec<Value>,
 }
 
+/// Cloudflare takes 1 for automatic or 30 to 86400 seconds, below 60 only on Enterprise plans
+const TTL_RANGE: std::ops::RangeInclusive<u32> = 30..=86400;
+
+/// The body that creates or updates a record, A and AAAA records point to `ip`. Settings that
+/// are not set are left out so Cloudflare keeps what the record has.
+fn record_body(record: &Record, ip: &str) -> Result<Value, DnsCliError> {
+    let mut body = json!({
+        "type": record.record_type.to_string(),
+        "name": record.name,
+    });
+    let settings = &record.settings;
+    if let Some(ttl) = settings.ttl {
+        if ttl != 1 && !TTL_RANGE.contains(&ttl) {
+            return Err(DnsCliError::Config(format!(
+                "Invalid TTL {} for {}, Cloudflare takes 1 for automatic or {} to {} seconds",
+                ttl,
+                record.name,
+                TTL_RANGE.start(),
+                TTL_RANGE.end()
+            )));
+        }
+        body["ttl"] = json!(ttl);
+    }
+    if let Some(proxied) = settings.proxied {
+        body["proxied"] = json!(proxied);
+    }
+    if let Some(comment) = &settings.comment {
+        body["comment"] = json!(comment);
+    }
+    if !settings.tags.is_empty() {
+        body["tags"] = json!(settings.tags);
+    }
+    let Some(content) = &record.content else {
+        body["content"] = json!(ip);
+        return Ok(body);
+    };
+    match record.record_type {
+        RecordType::SRV => {
+            body["data"] = json!({
+                "priority": content.priority,
+                "weight": content.weight,
+                "port": content.port,
+                "target": content.value,
+            })
+        }
+        RecordType::CAA => {
+            body["data"] = json!({
+                "flags": content.flags.unwrap_or_default(),
+                "tag": content.tag,
+                "value": content.value,
+            })
+        }
+        RecordType::MX => {
+            body["content"] = json!(content.value);
+            body["priority"] = json!(content.priority);
+        }
+        _ => body["content"] = json!(content.value),
+    }
+    Ok(body)
+}
+
+fn api_error(action: &str, errors: &[Value]) -> DnsCliError {
+    DnsCliError::Api(format!(
+        "{}, Cloudflare returned: {}",
+        action,
+        serde_json::to_string(errors).unwrap_or_default()
+    ))
+}
+
+impl From<DnsRecord> for DomainDetails {
+    fn from(record: DnsRecord) -> DomainDetails {
+        // Cloudflare keeps the priority of MX and SRV records out of the content
+        let content = match (record.type_field.as_str(), record.priority) {
+            ("MX" | "SRV", Some(priority)) => format!("{} {}", priority, record.content),
+            _ => record.content,
+        };
+        DomainDetails {
+            id: record.id,
+            name: record.name,
+            record_type: record.type_field,
+            content,
+            proxied: record.proxied,
+            ttl: record.ttl as u32,
+            modified_on: Some(record.modified_on),
+        }
+    }
+}
+
 impl CloudflareProvider {
-    pub async fn new() -> Self {
+    pub async fn new(mut account: ProviderAccount) -> Result<Self, DnsCliError> {
         let client = Client::new();
-        let mut config = CONFIG_SINGLETON.lock().await.get();
-        if config.cloudflare_config.api_token.is_empty() {
-            let token =
-                prompt_text("Please enter your cloudflare api token").expect("Could not get token");
-            config.cloudflare_config.api_token = token;
-            CONFIG_SINGLETON.lock().await.save(config.clone());
+        let ProviderCredentials::Cloudflare { mut api_token } = account.credentials.clone() else {
+            return Err(DnsCliError::Config(format!(
+                "Account {} is not a Cloudflare account",
+                account.name
+            )));
+        };
+        if api_token.is_empty() {
+            api_token = Secret::new(interaction::text(
+                &format!(
+                    "Please enter your cloudflare api token for {}",
+                    account.name
+                ),
+                &format!("set api_token of account {} in the config", account.name),
+            )?);
+            account.credentials = ProviderCredentials::Cloudflare {
+                api_token: api_token.clone(),
+            };
+            CONFIG_SINGLETON
+                .lock()
+                .await
+                .save_account(account.clone())?;
         }
 
-        Self { client, config }
+        Ok(Self {
+            client,
+            account,
+            api_token: api_token.expose().to_string(),
+        })
     }
-    async fn sync_zones(&mut self) {
-        let response: ZoneResponse = self
-            .client
-            .get(format!("{}/zones", CLOUDFLARE_API_URL))
+
+    /// Sends the request with the account token and parses the JSON response
+    async fn send<R: DeserializeOwned>(&self, request: RequestBuilder) -> Result<R, DnsCliError> {
+        let text_response = request
             .header(
                 reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", self.config.cloudflare_config.api_token),
+                format!("Bearer {}", self.api_token),
             )
             .send()
-            .await
-            .unwrap()
-            .json()
-            .await
-            .unwrap();
-        response
+            .await?
+            .text()
+            .await?;
+        serde_json::from_str(&text_response).map_err(|e| {
+            DnsCliError::Api(format!(
+                "Failed to parse response: {}\nResponse: {}",
+                e, text_response
+            ))
+        })
+    }
+
+    async fn sync_zones(&mut self) -> Result<(), DnsCliError> {
+        let response: ZoneResponse = self
+            .send(self.client.get(format!("{}/zones", CLOUDFLARE_API_URL)))
+            .await?;
+        let zones = response
             .result
-            .expect("DNS result list null")
-            .iter()
-            .for_each(|zone| {
-                if let Some(domain) = self.config.cloudflare_config.domains.get_mut(&zone.id) {
-                    domain.domain.clone_from(&zone.name);
-                } else {
-                    let ans = Confirm::new(
-                        format!("Do you want to add the domain: {}", zone.name).as_str(),
-                    )
-                    .with_default(false)
-                    .prompt();
-                    if let Ok(true) = ans {
-                        println!("Added domain: {}", zone.name);
-                        self.config.cloudflare_config.domains.insert(
-                            zone.id.clone(),
-                            Domain {
-                                domain: zone.name.clone(),
-                                records: vec![],
-                            },
-                        );
-                    }
-                }
-            });
-        CONFIG_SINGLETON.lock().await.save(self.config.clone());
+            .ok_or_else(|| api_error("Could not list zones", &response.errors))?;
+        for zone in zones.iter() {
+            if let Some(domain) = self.account.domains.get_mut(&zone.id) {
+                domain.domain.clone_from(&zone.name);
+            } else if interaction::add_zone(&zone.name)? {
+                status!("Added domain: {}", zone.name);
+                self.account.domains.insert(
+                    zone.id.clone(),
+                    Domain {
+                        domain: zone.name.clone(),
+                        records: vec![],
+                    },
+                );
+            }
+        }
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(self.account.clone())
+    }
+}
+impl DnsProvider for CloudflareProvider {
+    fn account(&self) -> &ProviderAccount {
+        &self.account
+    }
+
+    fn account_mut(&mut self) -> &mut ProviderAccount {
+        &mut self.account
+    }
+
+    async fn set_sub_domain(&self, record: &Record, id: String) -> Result<String, DnsCliError> {
+        let ip = match record.record_type.ip_version() {
+            Some(version) => get_current_ip(version).await?,
+            None => String::new(),
+        };
+        let mut record = record.clone();
+        // Records that follow the IP of this server are proxied unless told otherwise
+        if record.settings.proxied.is_none() {
+            record.settings.proxied = Some(record.content.is_none());
+        }
+        self.create_record(&record, &id, &ip).await
+    }
+
+    async fn create_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<String, DnsCliError> {
+        let url = format!("{}/zones/{}/dns_records", CLOUDFLARE_API_URL, zone_id);
+        let body = record_body(record, ip)?;
+        let response: DNSCreateResponse = self.send(self.client.post(url).json(&body)).await?;
+
+        match response.result {
+            Some(created) if response.success => Ok(created.id),
+            _ => Err(api_error(
+                &format!("Failed to create {}", record.name),
+                &response.errors,
+            )),
+        }
     }
 
-    async fn update_ip(&self, ip: &str, record: &Record, zone_id: String) {
+    async fn remove_sub_domain(&self, record: &Record, zone_id: String) -> Result<(), DnsCliError> {
         let url = format!(
             "{}/zones/{}/dns_records/{}",
             CLOUDFLARE_API_URL, zone_id, record.id
         );
-        let body = json!({
-        "type": "A",
-        "name": record.name,
-        "content": ip,
-        });
-        let text_response = self
-            .client
-            .patch(url)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", self.config.cloudflare_config.api_token),
-            )
-            .json(&body)
-            .send()
-            .await
-            .unwrap()
-            .text()
-            .await
-            .unwrap();
-        let response: UpdateResponse = serde_json::from_str(&text_response).unwrap();
+        let response: UpdateResponse = self.send(self.client.delete(url)).await?;
         if !response.success {
-            println!("Failed to update record: {:#?}", response);
-            println!("Request was {:#?}", body);
-
-            panic!("Error: {:#?}", response.errors)
-        } else {
-            println!("Updated {} to {}", record.name, ip);
+            return Err(api_error(
+                &format!("Failed to remove {}", record.name),
+                &response.errors,
+            ));
         }
+        status!("Removed {}", record.name);
+        Ok(())
     }
-}
-impl DnsProvider for CloudflareProvider {
-    async fn set_sub_domain(&self, record: &crate::config::Record, id: String) -> String {
-        let url = format!("{}/zones/{}/dns_records", CLOUDFLARE_API_URL, id);
-        let ip = get_current_ip().await.expect("Could not get current ip");
-        let body = json!({
-        "type": "A",
-        "name": record.name,
-        "proxied": true,
-        "content": ip,
-        });
-        let text_response = self
-            .client
-            .post(url)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", self.config.cloudflare_config.api_token),
-            )
-            .json(&body)
-            .send()
-            .await
-            .unwrap()
-            .text()
-            .await
-            .unwrap();
-        //println!("{:#?}", text_response);
-        let response: DNSCreateResponse = serde_json::from_str(&text_response)
-            .map_err(|e| {
-                println!("Failed to parse: {}", text_response);
-                e
-            })
-            .unwrap();
 
-        if response.success {
-            response.result.unwrap().id.clone()
-        } else {
-            print!("{}", text_response);
-            panic!("Error: {:#?}", response.errors)
-        }
+    fn check_record(&self, record: &Record, _zone_id: &str) -> Result<(), DnsCliError> {
+        record_body(record, "").map(|_| ())
     }
 
-    async fn remove_sub_domain(&self, record: &crate::config::Record, zone_id: String) {
+    async fn update_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<(), DnsCliError> {
         let url = format!(
             "{}/zones/{}/dns_records/{}",
             CLOUDFLARE_API_URL, zone_id, record.id
         );
-        let ip = get_current_ip().await.expect("Could not get current ip");
-        let body = json!({
-        "type": "A",
-        "name": record.name,
-        "content": ip,
-        });
-        let text_response = self
-            .client
-            .delete(url)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", self.config.cloudflare_config.api_token),
-            )
-            .json(&body)
-            .send()
-            .await
-            .unwrap()
-            .text()
-            .await
-            .unwrap();
-        println!("Response: {:#?}", text_response);
+        let body = record_body(record, ip)?;
+        let response: UpdateResponse = self.send(self.client.patch(url).json(&body)).await?;
+        if !response.success {
+            return Err(api_error(
+                &format!("Failed to update {}", record.name),
+                &response.errors,
+            ));
+        }
+        status!("Updated {} to {}", record.name, record.display_content(ip));
+        Ok(())
     }
 
-    async fn change_ip(&self, ip: &str) {
-        println!("Cloudflare: Updating records to {}", ip);
-        for (id, domain) in self.config.cloudflare_config.domains.iter() {
-            for record in domain.records.iter() {
-                self.update_ip(ip, record, id.clone()).await;
-            }
+    async fn get_record_content(
+        &self,
+        record: &Record,
+        zone_id: &str,
+    ) -> Result<String, DnsCliError> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            CLOUDFLARE_API_URL, zone_id, record.id
+        );
+        let response: DNSCreateResponse = self.send(self.client.get(url)).await?;
+        match response.result {
+            Some(live) if response.success => Ok(live.content),
+            _ => Err(api_error(
+                &format!("Failed to fetch {}", record.name),
+                &response.errors,
+            )),
         }
     }
 
-    async fn import(&mut self) {
-        self.sync_zones().await;
-        let ip = get_current_ip().await.expect("Could not get current ip");
-        for (id, domain) in self.config.cloudflare_config.domains.iter_mut() {
-            let response_txt = self
-                .client
-                .get(format!(
-                    "{}/zones/{}/dns_records?content={}",
-                    CLOUDFLARE_API_URL, id, ip
-                ))
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", self.config.cloudflare_config.api_token),
-                )
-                .send()
-                .await
-                .unwrap()
-                .text()
-                .await
-                .unwrap();
-
-            let mut resulting_domain_list = vec![];
-            let response: DNSListResponse = serde_json::from_str(&response_txt)
-                .map_err(|err| {
-                    println!("Could not parse response: {:#?}", err);
-                    println!("Response: {}", response_txt);
-                    err
-                })
-                .unwrap();
-            if let Some(records) = response.result {
+    async fn import(&mut self) -> Result<(), DnsCliError> {
+        self.sync_zones().await?;
+        let mut current_ips = vec![];
+        for version in IpVersion::ALL {
+            match get_current_ip(version).await {
+                Ok(ip) => current_ips.push((version, ip)),
+                Err(e) => status!("Skipping {} records: {}", version, e),
+            }
+        }
+        if current_ips.is_empty() {
+            return Err(DnsCliError::IpDetection(
+                "Could not get the current IPv4 or IPv6".to_string(),
+            ));
+        }
+        let zone_ids: Vec<String> = self.account.domains.keys().cloned().collect();
+        'zones: for id in zone_ids {
+            let mut imported = vec![];
+            for (version, ip) in current_ips.iter() {
+                let response: DNSListResponse = self
+                    .send(self.client.get(format!(
+                        "{}/zones/{}/dns_records?content={}",
+                        CLOUDFLARE_API_URL, id, ip
+                    )))
+                    .await?;
+                let Some(records) = response.result else {
+                    status!("Could not import, got errors {:#?}", response.errors);
+                    continue 'zones;
+                };
                 for record in records.iter() {
-                    if record.type_field == "A" {
-                        resulting_domain_list.push(Record {
-                            name: record.name.clone(),
-                            id: record.id.clone(),
-                            record_type: crate::config::RecordType::A,
-                        });
-                        if domain.records.iter().any(|r| r.id == record.id) {
-                            continue;
-                        }
-                        println!("Importing {}", record.name);
-                    }
-                }
-                for existing_record in domain.records.iter() {
-                    if resulting_domain_list
-                        .iter()
-                        .any(|r| r.id == existing_record.id)
-                    {
+                    let Some(record_type) = RecordType::from_api_name(&record.type_field) else {
+                        continue;
+                    };
+                    if record_type.ip_version() != Some(*version) {
                         continue;
                     }
-                    let ans = Confirm::new(
-                        format!(
-                            "{} not found on remote, you want to add it?",
-                            existing_record.name
-                        )
-                        .as_str(),
-                    )
-                    .with_default(false)
-                    .prompt();
-                    if let Ok(true) = ans {
-                        println!("TODO")
-                    }
+                    imported.push(Record {
+                        name: record.name.clone(),
+                        id: record.id.clone(),
+                        record_type,
+                        content: None,
+                        settings: RecordSettings::default(),
+                    });
                 }
-                domain.records = resulting_domain_list.clone();
-            } else {
-                println!("Could not import, got errors {:#?}", response.errors);
             }
+            let domain = self.account.domains.get_mut(&id).unwrap();
+            merge_imported(domain, imported, &current_ips)?;
         }
-        CONFIG_SINGLETON.lock().await.save(self.config.clone());
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(self.account.clone())
     }
 
-    async fn get_domain_details(
-        &self,
-        prefix: &str,
-    ) -> Result<crate::dns_provider::DomainDetails, Box<dyn std::error::Error>> {
+    async fn get_domain_details(&self, prefix: &str) -> Result<DomainDetails, DnsCliError> {
         // First, find the domain and record that matches the prefix
         let mut found_zone_id = None;
         let mut found_record_id = None;
         let mut full_domain_name = String::new();
 
         // Search through all domains to find the matching record
-        for (zone_id, domain) in self.config.cloudflare_config.domains.iter() {
+        for (zone_id, domain) in self.account.domains.iter() {
             for record in &domain.records {
                 // The record.name will be the full domain name (e.g., prefix.example.com)
                 if record.name.starts_with(prefix)
@@ -340,8 +444,9 @@ impl DnsProvider for CloudflareProvider {
         }
 
         // If we couldn't find a matching record, return an error
-        let zone_id =
-            found_zone_id.ok_or_else(|| format!("No domain found with prefix: {}", prefix))?;
+        let zone_id = found_zone_id.ok_or_else(|| {
+            DnsCliError::Config(format!("No domain found with prefix: {}", prefix))
+        })?;
 
         // If we found a record ID, fetch the specific record
         if let Some(record_id) = found_record_id {
@@ -350,46 +455,21 @@ impl DnsProvider for CloudflareProvider {
                 CLOUDFLARE_API_URL, zone_id, record_id
             );
 
-            let response = self
-                .client
-                .get(url)
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", self.config.cloudflare_config.api_token),
-                )
-                .send()
-                .await?;
-
-            if !response.status().is_success() {
-                return Err(
-                    format!("Failed to fetch domain details: HTTP {}", response.status()).into(),
-                );
-            }
-
-            let text_response = response.text().await?;
-            let response: DNSCreateResponse =
-                serde_json::from_str(&text_response).map_err(|e| {
-                    format!(
-                        "Failed to parse response: {}\nResponse: {}",
-                        e, text_response
-                    )
-                })?;
+            let response: DNSCreateResponse = self.send(self.client.get(url)).await?;
 
             if !response.success {
-                return Err(format!("Cloudflare API error: {:?}", response.errors).into());
+                return Err(api_error(
+                    "Failed to fetch domain details",
+                    &response.errors,
+                ));
             }
 
             if let Some(record) = response.result {
-                return Ok(crate::dns_provider::DomainDetails {
-                    name: record.name,
-                    record_type: record.type_field,
-                    content: record.content,
-                    proxied: record.proxied,
-                    ttl: record.ttl as u32,
-                    modified_on: Some(record.modified_on),
-                });
+                Ok(record.into())
             } else {
-                return Err("No record details returned from Cloudflare".into());
+                Err(DnsCliError::Api(
+                    "No record details returned from Cloudflare".to_string(),
+                ))
             }
         } else {
             // Try to find the record by searching the zone's DNS records
@@ -398,48 +478,151 @@ impl DnsProvider for CloudflareProvider {
                 CLOUDFLARE_API_URL, zone_id, full_domain_name
             );
 
-            let response = self
-                .client
-                .get(url)
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", self.config.cloudflare_config.api_token),
-                )
-                .send()
-                .await?;
-
-            if !response.status().is_success() {
-                return Err(
-                    format!("Failed to fetch domain details: HTTP {}", response.status()).into(),
-                );
-            }
-
-            let text_response = response.text().await?;
-            let response: DNSListResponse = serde_json::from_str(&text_response).map_err(|e| {
-                format!(
-                    "Failed to parse response: {}\nResponse: {}",
-                    e, text_response
-                )
-            })?;
+            let response: DNSListResponse = self.send(self.client.get(url)).await?;
 
             if !response.success {
-                return Err(format!("Cloudflare API error: {:?}", response.errors).into());
+                return Err(api_error(
+                    "Failed to fetch domain details",
+                    &response.errors,
+                ));
             }
 
-            if let Some(records) = response.result {
-                if let Some(record) = records.first() {
-                    return Ok(crate::dns_provider::DomainDetails {
-                        name: record.name.clone(),
-                        record_type: record.type_field.clone(),
-                        content: record.content.clone(),
-                        proxied: record.proxied,
-                        ttl: record.ttl as u32,
-                        modified_on: Some(record.modified_on.clone()),
-                    });
-                }
+            if let Some(record) = response
+                .result
+                .and_then(|records| records.into_iter().next())
+            {
+                return Ok(record.into());
             }
 
-            return Err(format!("No DNS record found with prefix: {}", prefix).into());
+            Err(DnsCliError::Api(format!(
+                "No DNS record found with prefix: {}",
+                prefix
+            )))
+        }
+    }
+
+    async fn list_records(&self, zone_id: &str) -> Result<Vec<DomainDetails>, DnsCliError> {
+        let mut records = vec![];
+        let mut page = 1;
+        loop {
+            let url = format!(
+                "{}/zones/{}/dns_records?page={}&per_page={}",
+                CLOUDFLARE_API_URL, zone_id, page, PAGE_SIZE
+            );
+            let response: DNSListResponse = self.send(self.client.get(url)).await?;
+            let result = match response.result {
+                Some(result) if response.success => result,
+                _ => return Err(api_error("Failed to list records", &response.errors)),
+            };
+            records.extend(result.into_iter().map(DomainDetails::from));
+            match response.result_info {
+                Some(info) if info.page < info.total_pages => page = info.page + 1,
+                _ => return Ok(records),
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{record_body, DNSListResponse};
+    use crate::config::{Record, RecordSettings, RecordType};
+    use crate::dns_provider::DomainDetails;
+    use crate::record_content::RecordContent;
+    use crate::test_fixtures::record;
+
+    #[test]
+    fn record_body_puts_type_specific_fields_where_cloudflare_wants_them() {
+        let a = record_body(&record("a.example.com", RecordType::A), "192.0.2.1").unwrap();
+        assert_eq!(a["content"], "192.0.2.1");
+
+        let mx = RecordContent {
+            value: "mail.example.com".to_string(),
+            priority: Some(10),
+            ..Default::default()
+        };
+        let mx = Record {
+            content: Some(mx),
+            ..record("example.com", RecordType::MX)
+        };
+        let body = record_body(&mx, "").unwrap();
+        assert_eq!(body["type"], "MX");
+        assert_eq!(body["content"], "mail.example.com");
+        assert_eq!(body["priority"], 10);
+
+        let srv = RecordContent {
+            value: "sip.example.com".to_string(),
+            priority: Some(10),
+            weight: Some(5),
+            port: Some(5060),
+            ..Default::default()
+        };
+        let srv = Record {
+            content: Some(srv),
+            ..record("_sip._tcp.example.com", RecordType::SRV)
+        };
+        let body = record_body(&srv, "").unwrap();
+        assert_eq!(body["data"]["port"], 5060);
+        assert_eq!(body["data"]["target"], "sip.example.com");
+        assert!(body.get("content").is_none());
+
+        let caa = RecordContent {
+            value: "letsencrypt.org".to_string(),
+            tag: Some("issue".to_string()),
+            ..Default::default()
+        };
+        let caa = Record {
+            content: Some(caa),
+            ..record("example.com", RecordType::CAA)
+        };
+        let body = record_body(&caa, "").unwrap();
+        assert_eq!(body["data"]["flags"], 0);
+        assert_eq!(body["data"]["tag"], "issue");
+    }
+
+    #[test]
+    fn record_body_sends_only_the_settings_that_are_set() {
+        let mut a = record("ssh.example.com", RecordType::A);
+        let body = record_body(&a, "192.0.2.1").unwrap();
+        assert!(body.get("proxied").is_none());
+        assert!(body.get("ttl").is_none());
+
+        a.settings = RecordSettings {
+            ttl: Some(300),
+            proxied: Some(false),
+            comment: Some("ssh".to_string()),
+            tags: vec!["team:infra".to_string()],
+        };
+        let body = record_body(&a, "192.0.2.1").unwrap();
+        assert_eq!(body["ttl"], 300);
+        assert_eq!(body["proxied"], false);
+        assert_eq!(body["comment"], "ssh");
+        assert_eq!(body["tags"][0], "team:infra");
+
+        a.settings.ttl = Some(10);
+        assert!(record_body(&a, "192.0.2.1").is_err());
+    }
+
+    #[test]
+    fn list_response_has_paging_and_converts_to_details() {
+        let response: DNSListResponse = serde_json::from_str(
+            r#"{
+                "result": [{
+                    "id": "abc", "name": "mail.example.com", "type": "MX",
+                    "content": "mx.example.net", "priority": 10, "proxiable": false,
+                    "proxied": false, "ttl": 1, "comment": null, "tags": [],
+                    "created_on": "2024-01-01T00:00:00Z", "modified_on": "2024-02-01T00:00:00Z"
+                }],
+                "success": true, "errors": [], "messages": [],
+                "result_info": {"page": 1, "per_page": 500, "count": 1, "total_count": 1, "total_pages": 1}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.result_info.unwrap().total_pages, 1);
+        let details: DomainDetails = response.result.unwrap().remove(0).into();
+        assert_eq!(details.id, "abc");
+        assert_eq!(details.record_type, "MX");
+        assert_eq!(details.content, "10 mx.example.net");
+        assert_eq!(details.modified_on.as_deref(), Some("2024-02-01T00:00:00Z"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use crate::output::status;
+use crate::webhook_notifier::{EventKind, IpChange, NotifyEvent, WebhookNotifier};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Runs a local program for events, the event is passed as `DNS_CLI_*` environment variables
+/// and as JSON on stdin
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommandHook {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    /// Seconds the program may run before it is killed
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+    #[serde(default = "default_hook_events")]
+    events: Vec<EventKind>,
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+/// Hooks usually restart or reload something, which only makes sense when the IP changed
+pub fn default_hook_events() -> Vec<EventKind> {
+    vec![EventKind::IpChanged]
+}
+
+struct HookOutput {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+}
+
+impl CommandHook {
+    pub fn new(
+        command: String,
+        args: Vec<String>,
+        timeout: u64,
+        events: Vec<EventKind>,
+    ) -> CommandHook {
+        CommandHook {
+            command,
+            args,
+            timeout,
+            events,
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn events(&self) -> &[EventKind] {
+        &self.events
+    }
+
+    async fn run(&self, event: &NotifyEvent) -> Result<HookOutput, String> {
+        let mut command = Command::new(&self.command);
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Dropping the child when the timeout hits kills it
+            .kill_on_drop(true);
+        for (name, value) in event.placeholders() {
+            command.env(format!("DNS_CLI_{}", name.to_uppercase()), value);
+        }
+        let mut child = command.spawn().map_err(|e| e.to_string())?;
+        let input = event_json(event).to_string();
+        let mut stdin = child.stdin.take().unwrap();
+        let run = async move {
+            // A program that doesn't read stdin closes it early, that is not an error
+            let _ = stdin.write_all(input.as_bytes()).await;
+            drop(stdin);
+            child.wait_with_output().await
+        };
+        let output = tokio::time::timeout(Duration::from_secs(self.timeout), run)
+            .await
+            .map_err(|_| format!("timed out after {}s and was killed", self.timeout))?
+            .map_err(|e| e.to_string())?;
+        Ok(HookOutput {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+/// The event as a JSON object with the same fields as the environment variables, the record
+/// lists of an IP change are arrays
+fn event_json(event: &NotifyEvent) -> Value {
+    let mut object: Map<String, Value> = event
+        .placeholders()
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), Value::String(value)))
+        .collect();
+    if let NotifyEvent::IpChanged(IpChange {
+        records_updated,
+        failures,
+        ..
+    }) = event
+    {
+        object.insert("records_updated".to_string(), json!(records_updated));
+        object.insert("failures".to_string(), json!(failures));
+    }
+    Value::Object(object)
+}
+
+impl WebhookNotifier for CommandHook {
+    async fn notify(&self, event: &NotifyEvent) {
+        match self.run(event).await {
+            Ok(output) if output.status.success() => {
+                status!("Hook {} finished", self.command);
+            }
+            Ok(output) => {
+                // Programs usually explain failures on stderr, fall back to stdout when they don't
+                let details = match output.stderr.trim() {
+                    "" => output.stdout.trim(),
+                    stderr => stderr,
+                };
+                eprintln!(
+                    "Hook {} exited with {}: {}",
+                    self.command, output.status, details
+                )
+            }
+            Err(err) => eprintln!("Hook {} failed: {}", self.command, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{event_json, CommandHook};
+    use crate::webhook_notifier::{EventKind, NotifyEvent};
+
+    #[test]
+    fn event_json_has_record_lists_as_arrays() {
+        let json = event_json(&NotifyEvent::sample(EventKind::IpChanged));
+        assert_eq!(json["event"], "ip_changed");
+        assert_eq!(json["new_ip"], "192.0.2.2");
+        assert_eq!(json["records_updated"][0], "test.example.com");
+        assert!(json["failures"].as_array().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_passes_env_and_stdin_and_captures_status() {
+        let hook = CommandHook::new(
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                "echo \"$DNS_CLI_OLD_IP $DNS_CLI_NEW_IP\"; cat; exit 3".to_string(),
+            ],
+            5,
+            vec![EventKind::IpChanged],
+        );
+        let output = hook
+            .run(&NotifyEvent::sample(EventKind::IpChanged))
+            .await
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        let mut lines = output.stdout.lines();
+        assert_eq!(lines.next(), Some("192.0.2.1 192.0.2.2"));
+        let json: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(json["old_ip"], "192.0.2.1");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_kills_program_after_timeout() {
+        let hook = CommandHook::new(
+            "sleep".to_string(),
+            vec!["10".to_string()],
+            1,
+            vec![EventKind::IpChanged],
+        );
+        let err = hook
+            .run(&NotifyEvent::sample(EventKind::IpChanged))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.contains("timed out"));
+    }
+}
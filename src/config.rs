@@ -1,25 +1,144 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
 extern crate lazy_static;
 use lazy_static::lazy_static;
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, fmt, path::PathBuf};
 use tokio::sync::Mutex;
 
-use crate::webhook_notifier::WebhookNotifierType;
+use crate::error::DnsCliError;
+use crate::ip_handler::{IpDetectionConfig, IpVersion};
+use crate::output::{print_table, status, Output};
+use crate::record_content::RecordContent;
+use crate::secret::Secret;
+use crate::state::State;
+use crate::webhook_notifier::{numbered, WebhookNotifierType};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
-    pub cloudflare_config: CloudflareConfig,
+    #[serde(default)]
+    pub accounts: Vec<ProviderAccount>,
 
     #[serde(default)]
     pub webhooks: Vec<WebhookNotifierType>,
+
+    #[serde(default)]
+    pub ip_detection: IpDetectionConfig,
 }
 
+/// A named account at a DNS provider together with the zones and records tracked in it
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct CloudflareConfig {
-    pub api_token: String,
+pub struct ProviderAccount {
+    pub name: String,
+    #[serde(flatten)]
+    pub credentials: ProviderCredentials,
+    /// Keyed by zone id, for GoDaddy the zone id is the domain name
+    #[serde(default)]
     pub domains: HashMap<String, Domain>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum ProviderCredentials {
+    Cloudflare { api_token: Secret },
+    GoDaddy { api_key: Secret, secret: Secret },
+}
+
+/// The DNS providers a set of tracked domains can live in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    Cloudflare,
+    GoDaddy,
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderKind::Cloudflare => write!(f, "cloudflare"),
+            ProviderKind::GoDaddy => write!(f, "godaddy"),
+        }
+    }
+}
+
+impl ProviderAccount {
+    /// An account without credentials, the provider asks for them on first use
+    pub fn new(name: String, kind: ProviderKind) -> ProviderAccount {
+        let credentials = match kind {
+            ProviderKind::Cloudflare => ProviderCredentials::Cloudflare {
+                api_token: Secret::default(),
+            },
+            ProviderKind::GoDaddy => ProviderCredentials::GoDaddy {
+                api_key: Secret::default(),
+                secret: Secret::default(),
+            },
+        };
+        ProviderAccount {
+            name,
+            credentials,
+            domains: HashMap::new(),
+        }
+    }
+
+    pub fn kind(&self) -> ProviderKind {
+        match self.credentials {
+            ProviderCredentials::Cloudflare { .. } => ProviderKind::Cloudflare,
+            ProviderCredentials::GoDaddy { .. } => ProviderKind::GoDaddy,
+        }
+    }
+}
+
+/// Config layout from before provider accounts, only used to migrate old config files
+#[derive(Deserialize)]
+struct LegacyConfig {
+    cloudflare_config: Option<LegacyCloudflareConfig>,
+    godaddy_config: Option<LegacyGoDaddyConfig>,
+    #[serde(default)]
+    webhooks: Vec<WebhookNotifierType>,
+}
+
+#[derive(Deserialize)]
+struct LegacyCloudflareConfig {
+    api_token: String,
+    domains: HashMap<String, Domain>,
+}
+
+#[derive(Deserialize)]
+struct LegacyGoDaddyConfig {
+    api_key: String,
+    secret: String,
+    domains: HashMap<String, Domain>,
+}
+
+impl From<LegacyConfig> for Config {
+    fn from(legacy: LegacyConfig) -> Config {
+        let mut accounts = vec![];
+        if let Some(cloudflare) = legacy.cloudflare_config {
+            accounts.push(ProviderAccount {
+                name: ProviderKind::Cloudflare.to_string(),
+                credentials: ProviderCredentials::Cloudflare {
+                    api_token: Secret::new(cloudflare.api_token),
+                },
+                domains: cloudflare.domains,
+            });
+        }
+        if let Some(godaddy) = legacy.godaddy_config {
+            accounts.push(ProviderAccount {
+                name: ProviderKind::GoDaddy.to_string(),
+                credentials: ProviderCredentials::GoDaddy {
+                    api_key: Secret::new(godaddy.api_key),
+                    secret: Secret::new(godaddy.secret),
+                },
+                domains: godaddy.domains,
+            });
+        }
+        Config {
+            accounts,
+            webhooks: legacy.webhooks,
+            ip_detection: IpDetectionConfig::default(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Domain {
     pub records: Vec<Record>,
@@ -31,91 +150,529 @@ pub struct Record {
     pub id: String,
     pub name: String,
     pub record_type: RecordType,
+    /// The content of records that don't point to the IP of this server, None for A and AAAA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<RecordContent>,
+    #[serde(flatten)]
+    pub settings: RecordSettings,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Provider settings of a record. Settings that are not set get the provider default when the
+/// record is created and are left as they are at the provider when it is updated.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordSettings {
+    /// Time to live in seconds, 1 is automatic at Cloudflare
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    /// Whether Cloudflare proxies the traffic, new A and AAAA records are proxied when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl fmt::Display for RecordSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(ttl) = self.ttl {
+            parts.push(format!("ttl {}", ttl));
+        }
+        match self.proxied {
+            Some(true) => parts.push("proxied".to_string()),
+            Some(false) => parts.push("DNS only".to_string()),
+            None => {}
+        }
+        if let Some(comment) = &self.comment {
+            parts.push(format!("comment {:?}", comment));
+        }
+        if !self.tags.is_empty() {
+            parts.push(format!("tags {}", self.tags.join(", ")));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl RecordSettings {
+    /// Checks the settings that don't depend on the provider
+    pub fn validate(&self, record_type: RecordType, name: &str) -> Result<(), DnsCliError> {
+        let proxiable = matches!(
+            record_type,
+            RecordType::A | RecordType::AAAA | RecordType::CNAME
+        );
+        if self.proxied == Some(true) && !proxiable {
+            return Err(DnsCliError::Config(format!(
+                "Invalid {} record {}: only A, AAAA and CNAME records can be proxied",
+                record_type, name
+            )));
+        }
+        if self.ttl == Some(0) {
+            return Err(DnsCliError::Config(format!(
+                "Invalid {} record {}: the TTL must be at least 1 second",
+                record_type, name
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Record {
+    /// What the record points to, A and AAAA records point to `ip`
+    pub fn display_content(&self, ip: &str) -> String {
+        match &self.content {
+            Some(content) => content.rdata(self.record_type),
+            None => ip.to_string(),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum RecordType {
     A,
-    AAA,
+    /// Older config files spelled this variant `AAA`
+    #[serde(alias = "AAA")]
+    AAAA,
+    CNAME,
+    TXT,
+    MX,
+    SRV,
+    CAA,
 }
 
 impl RecordType {
-    pub fn to_string(&self) -> String {
+    /// The IP version a record of this type points to, None for records with their own content
+    pub fn ip_version(&self) -> Option<IpVersion> {
         match self {
-            RecordType::A => "A".to_string(),
-            RecordType::AAA => "AAAA".to_string(),
+            RecordType::A => Some(IpVersion::V4),
+            RecordType::AAAA => Some(IpVersion::V6),
+            _ => None,
         }
     }
+
+    /// Parses the record type as returned by the DNS provider, returns None for unsupported types
+    pub fn from_api_name(name: &str) -> Option<RecordType> {
+        match name {
+            "A" => Some(RecordType::A),
+            "AAAA" => Some(RecordType::AAAA),
+            "CNAME" => Some(RecordType::CNAME),
+            "TXT" => Some(RecordType::TXT),
+            "MX" => Some(RecordType::MX),
+            "SRV" => Some(RecordType::SRV),
+            "CAA" => Some(RecordType::CAA),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecordType::A => "A",
+            RecordType::AAAA => "AAAA",
+            RecordType::CNAME => "CNAME",
+            RecordType::TXT => "TXT",
+            RecordType::MX => "MX",
+            RecordType::SRV => "SRV",
+            RecordType::CAA => "CAA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Finds the id of the zone a record name belongs to. When zones are nested, e.g.
+/// `example.com` and `dev.example.com`, the longest matching zone name wins.
+pub fn find_zone_id(domains: &HashMap<String, Domain>, name: &str) -> Option<String> {
+    let name = normalize_name(name);
+    domains
+        .iter()
+        .filter(|(_, domain)| {
+            let zone = normalize_name(&domain.domain);
+            name == zone || name.ends_with(&format!(".{}", zone))
+        })
+        .max_by_key(|(_, domain)| normalize_name(&domain.domain).len())
+        .map(|(id, _)| id.clone())
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+/// The accounts with their zones, the notifiers and the IP sources
+impl Output for Config {
+    fn print_table(&self) {
+        let mut rows = vec![];
+        for account in &self.accounts {
+            let mut zones: Vec<&Domain> = account.domains.values().collect();
+            zones.sort_by(|a, b| a.domain.cmp(&b.domain));
+            for zone in zones {
+                rows.push(vec![
+                    account.name.clone(),
+                    account.kind().to_string(),
+                    zone.domain.clone(),
+                    zone.records.len().to_string(),
+                ]);
+            }
+            if account.domains.is_empty() {
+                let kind = account.kind().to_string();
+                rows.push(vec![account.name.clone(), kind, "".into(), "".into()]);
+            }
+        }
+        print_table(&["ACCOUNT", "PROVIDER", "ZONE", "RECORDS"], &rows);
+
+        println!();
+        numbered(&self.webhooks).print_table();
+
+        println!();
+        let rows: Vec<Vec<String>> = self
+            .ip_detection
+            .sources
+            .iter()
+            .map(|source| {
+                vec![
+                    source
+                        .version
+                        .map(|version| version.to_string())
+                        .unwrap_or_else(|| "both".to_string()),
+                    serde_json::to_string(&source.source).unwrap_or_default(),
+                ]
+            })
+            .collect();
+        print_table(&["VERSION", "IP SOURCE"], &rows);
+    }
 }
 
 impl Config {
+    /// All accounts of a provider, in the order they are defined in the config
+    pub fn accounts_of(&self, kind: ProviderKind) -> Vec<ProviderAccount> {
+        self.accounts
+            .iter()
+            .filter(|account| account.kind() == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Replaces the account with the same name, or adds it if it is new
+    pub fn set_account(&mut self, account: ProviderAccount) {
+        match self.accounts.iter_mut().find(|a| a.name == account.name) {
+            Some(existing) => *existing = account,
+            None => self.accounts.push(account),
+        }
+    }
+
     pub(crate) fn add_webhook(&mut self, webhook: WebhookNotifierType) {
         self.webhooks.push(webhook);
     }
 
+    /// Removes the one webhook that matches, given its index and the webhook. Fails when none
+    /// or several match, notifiers can share a url, a host or a command, so only their number
+    /// in `notify ls` tells them apart.
+    pub(crate) fn remove_webhook<F>(
+        &mut self,
+        target: &str,
+        mut matches: F,
+    ) -> Result<WebhookNotifierType, DnsCliError>
+    where
+        F: FnMut(usize, &WebhookNotifierType) -> bool,
+    {
+        let found: Vec<usize> = self
+            .webhooks
+            .iter()
+            .enumerate()
+            .filter(|(index, webhook)| matches(*index, webhook))
+            .map(|(index, _)| index)
+            .collect();
+        match found[..] {
+            [] => Err(DnsCliError::Config(
+                "No matching notifier found".to_string(),
+            )),
+            [index] => Ok(self.webhooks.remove(index)),
+            _ => Err(DnsCliError::Config(format!(
+                "{} matches {} notifiers, pass the number `notify ls` shows for the one to remove",
+                target,
+                found.len()
+            ))),
+        }
+    }
+
     pub(crate) fn get_default_config() -> Config {
         Config {
-            cloudflare_config: CloudflareConfig {
-                api_token: "".to_string(),
-                domains: HashMap::new(),
-            },
+            accounts: vec![ProviderAccount::new(
+                ProviderKind::Cloudflare.to_string(),
+                ProviderKind::Cloudflare,
+            )],
             webhooks: vec![],
+            ip_detection: IpDetectionConfig::default(),
         }
     }
 
-    pub fn write(&self, path: &PathBuf) {
-        let config_json = serde_json::to_string_pretty(&self).unwrap();
-        std::fs::write(path, config_json).unwrap();
+    pub fn write(&self, path: &PathBuf) -> Result<(), DnsCliError> {
+        let config_json = serde_json::to_string_pretty(&self)
+            .map_err(|e| DnsCliError::Config(format!("Could not serialize config: {}", e)))?;
+        std::fs::write(path, config_json)?;
+        Ok(())
     }
 
-    pub(crate) fn get_config(path: &PathBuf) -> Option<Config> {
+    pub(crate) fn get_config(path: &PathBuf) -> Result<Config, DnsCliError> {
         if !path.exists() {
-            println!(
+            return Err(DnsCliError::Config(format!(
                 "No config file found for path: {:?}, maybe run the `init` command first?",
                 path
+            )));
+        }
+        let config = std::fs::read_to_string(path)?;
+
+        let (config, migrated) = Config::parse(&config).map_err(|e| {
+            DnsCliError::Config(format!(
+                "Failed to parse your config at {:?}, please validate it: {}",
+                path, e
+            ))
+        })?;
+        if migrated {
+            status!(
+                "Migrated config at {:?} to the provider accounts format",
+                path
             );
-            return None;
+            config.write(path)?;
         }
-        let config = std::fs::read_to_string(path).unwrap();
+        let mut config = config;
+        config.resolve_secrets()?;
+        Ok(config)
+    }
 
-        let config: Config =
-            serde_json::from_str(&config).expect("Failed to parse your config, please validate it");
-        Some(config)
+    /// Reads the secrets that are kept in environment variables, files or the OS keyring
+    fn resolve_secrets(&mut self) -> Result<(), DnsCliError> {
+        for account in &mut self.accounts {
+            let name = &account.name;
+            match &mut account.credentials {
+                ProviderCredentials::Cloudflare { api_token } => {
+                    api_token.resolve(&format!("api_token of account {}", name))?
+                }
+                ProviderCredentials::GoDaddy { api_key, secret } => {
+                    api_key.resolve(&format!("api_key of account {}", name))?;
+                    secret.resolve(&format!("secret of account {}", name))?;
+                }
+            }
+        }
+        for webhook in &mut self.webhooks {
+            webhook.resolve_secrets()?;
+        }
+        Ok(())
     }
 
-    pub(crate) fn get_default_config_path() -> PathBuf {
-        home::home_dir()
-            .unwrap()
-            .join(".config/dns-cli-config.json")
+    /// The config with the secrets written in it hidden, references to secrets are shown
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        for account in &mut config.accounts {
+            account.credentials = match &account.credentials {
+                ProviderCredentials::Cloudflare { api_token } => ProviderCredentials::Cloudflare {
+                    api_token: api_token.redacted(),
+                },
+                ProviderCredentials::GoDaddy { api_key, secret } => ProviderCredentials::GoDaddy {
+                    api_key: api_key.redacted(),
+                    secret: secret.redacted(),
+                },
+            };
+        }
+        for webhook in &mut config.webhooks {
+            *webhook = webhook.redacted();
+        }
+        config
+    }
+
+    /// Parses a config file, migrating the old single provider layout if needed.
+    /// Returns whether the config was migrated.
+    fn parse(config: &str) -> Result<(Config, bool), serde_json::Error> {
+        let value: serde_json::Value = serde_json::from_str(config)?;
+        if value.get("cloudflare_config").is_some() || value.get("godaddy_config").is_some() {
+            let legacy: LegacyConfig = serde_json::from_value(value)?;
+            return Ok((legacy.into(), true));
+        }
+        Ok((serde_json::from_value(value)?, false))
     }
 
-    pub(crate) fn get_config_path(custom_path: Option<PathBuf>) -> PathBuf {
-        custom_path.unwrap_or(Config::get_default_config_path())
+    pub(crate) fn get_default_config_path() -> Result<PathBuf, DnsCliError> {
+        let home = home::home_dir().ok_or_else(|| {
+            DnsCliError::Config("Could not find your home dir, pass a config path with -c".into())
+        })?;
+        Ok(home.join(".config/dns-cli-config.json"))
+    }
+
+    pub(crate) fn get_config_path(custom_path: Option<PathBuf>) -> Result<PathBuf, DnsCliError> {
+        match custom_path {
+            Some(path) => Ok(path),
+            None => Config::get_default_config_path(),
+        }
     }
 }
 
 pub struct ConfigSingleton {
     config: Option<Config>,
     path: Option<PathBuf>,
+    state_path: Option<PathBuf>,
 }
 impl ConfigSingleton {
-    pub fn init(&mut self, path: PathBuf) {
-        self.config = Some(Config::get_config(&path).unwrap());
+    pub fn init(&mut self, path: PathBuf) -> Result<(), DnsCliError> {
+        self.config = Some(Config::get_config(&path)?);
         self.path = Some(path);
+        Ok(())
+    }
+    /// Uses another state file than the one next to the config file
+    pub fn set_state_path(&mut self, state_path: Option<PathBuf>) {
+        self.state_path = state_path;
+    }
+    pub fn state_path(&self) -> PathBuf {
+        self.state_path
+            .clone()
+            .unwrap_or_else(|| State::default_path(self.path.as_ref().unwrap()))
+    }
+    /// Whether the state files older versions kept in the home dir belong to this config. They
+    /// were shared by every config, so only the default config without `--state` takes them.
+    pub fn takes_legacy_state(&self) -> bool {
+        self.state_path.is_none()
+            && self.path.is_some()
+            && Config::get_default_config_path().ok() == self.path
+    }
+    /// Reads the config file again from the path it was first loaded from
+    pub fn reload(&mut self) -> Result<(), DnsCliError> {
+        let path = self.path.clone().unwrap();
+        self.init(path)
     }
     pub fn get(&self) -> Config {
         let config = self.config.clone();
         config.unwrap()
     }
-    pub fn save(&mut self, config: Config) {
+    pub fn save(&mut self, config: Config) -> Result<(), DnsCliError> {
         let path = self.path.clone().unwrap();
+        config.write(&path)?;
         self.config = Some(config);
-        self.config.clone().unwrap().write(&path);
+        Ok(())
+    }
+    /// Saves a single account, leaving changes other providers made to their accounts intact
+    pub fn save_account(&mut self, account: ProviderAccount) -> Result<(), DnsCliError> {
+        let mut config = self.get();
+        config.set_account(account);
+        self.save(config)
     }
 }
 lazy_static! {
     pub static ref CONFIG_SINGLETON: Mutex<ConfigSingleton> = Mutex::new(ConfigSingleton {
         config: None,
-        path: None
+        path: None,
+        state_path: None,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{find_zone_id, Config, Domain, ProviderCredentials, ProviderKind};
+    use crate::command_hook::CommandHook;
+    use crate::error::DnsCliError;
+    use crate::webhook_notifier::WebhookNotifierType;
+    use std::collections::HashMap;
+
+    fn zones(names: &[&str]) -> HashMap<String, Domain> {
+        names
+            .iter()
+            .map(|name| {
+                (
+                    format!("id-{}", name),
+                    Domain {
+                        records: vec![],
+                        domain: name.to_string(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_zone_id_matches_deep_subdomains_and_apex() {
+        let domains = zones(&["example.com", "example.co.uk"]);
+        assert_eq!(
+            find_zone_id(&domains, "a.b.example.com"),
+            Some("id-example.com".to_string())
+        );
+        assert_eq!(
+            find_zone_id(&domains, "example.co.uk"),
+            Some("id-example.co.uk".to_string())
+        );
+        assert_eq!(
+            find_zone_id(&domains, "www.Example.co.uk."),
+            Some("id-example.co.uk".to_string())
+        );
+        assert_eq!(find_zone_id(&domains, "notexample.com"), None);
+    }
+
+    #[test]
+    fn find_zone_id_prefers_longest_suffix() {
+        let domains = zones(&["example.com", "dev.example.com"]);
+        assert_eq!(
+            find_zone_id(&domains, "api.dev.example.com"),
+            Some("id-dev.example.com".to_string())
+        );
+        assert_eq!(
+            find_zone_id(&domains, "api.example.com"),
+            Some("id-example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_migrates_legacy_config() {
+        let legacy = r#"{
+            "cloudflare_config": {
+                "api_token": "token",
+                "domains": {
+                    "zone-id": {
+                        "domain": "example.com",
+                        "records": [{"id": "1", "name": "a.example.com", "record_type": "AAA"}]
+                    }
+                }
+            },
+            "webhooks": []
+        }"#;
+        let (config, migrated) = Config::parse(legacy).unwrap();
+        assert!(migrated);
+        assert_eq!(config.accounts.len(), 1);
+        let account = &config.accounts[0];
+        assert_eq!(account.name, "cloudflare");
+        assert_eq!(account.kind(), ProviderKind::Cloudflare);
+        assert!(matches!(
+            &account.credentials,
+            ProviderCredentials::Cloudflare { api_token } if api_token.expose() == "token"
+        ));
+        assert_eq!(account.domains["zone-id"].records.len(), 1);
+
+        let written = serde_json::to_string(&config).unwrap();
+        let (reparsed, migrated) = Config::parse(&written).unwrap();
+        assert!(!migrated);
+        assert_eq!(reparsed.accounts[0].domains.len(), 1);
+    }
+
+    #[test]
+    fn remove_webhook_refuses_a_target_that_matches_several() {
+        let hook = |arg: &str| {
+            WebhookNotifierType::CommandHook(CommandHook::new(
+                "notify-send".to_string(),
+                vec![arg.to_string()],
+                10,
+                vec![],
+            ))
+        };
+        let mut config = Config::get_default_config();
+        config.webhooks = vec![hook("home"), hook("work")];
+
+        let err = config
+            .remove_webhook("notify-send", |_, webhook| webhook.url() == "notify-send")
+            .unwrap_err();
+        assert!(matches!(err, DnsCliError::Config(message) if message.contains("matches 2")));
+        assert_eq!(config.webhooks.len(), 2);
+
+        let removed = config.remove_webhook("2", |index, _| index == 1).unwrap();
+        let json = |webhook: &WebhookNotifierType| serde_json::to_value(webhook).unwrap();
+        assert_eq!(json(&removed), json(&hook("work")));
+        assert_eq!(config.webhooks.len(), 1);
+        assert_eq!(json(&config.webhooks[0]), json(&hook("home")));
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use crate::config::CONFIG_SINGLETON;
+use crate::error::DnsCliError;
+use crate::output::status;
+
+/// How often the daemon checks and how long it waits after failed checks
+pub struct WatchOptions {
+    pub interval: Duration,
+    /// A random delay up to this long is added to every wait so many machines don't check in lockstep
+    pub jitter: Duration,
+    pub max_backoff: Duration,
+    /// Compare the live content of the records with the IP on every check
+    pub reconcile: bool,
+}
+
+enum Signal {
+    Reload,
+    Shutdown,
+}
+
+/// Runs `check` until SIGTERM or Ctrl-C, a check that is running when the signal arrives is
+/// finished first. SIGHUP reloads the config file and checks right away.
+pub async fn watch(options: WatchOptions, debug: bool, dry_run: bool) -> Result<(), DnsCliError> {
+    let mut signals = Signals::new()?;
+    let mut failures = 0;
+    loop {
+        let config = CONFIG_SINGLETON.lock().await.get();
+        match crate::check_all_accounts(config, debug, dry_run, false, options.reconcile).await {
+            Ok(()) => failures = 0,
+            Err(err) => {
+                failures += 1;
+                eprintln!("{}", err);
+            }
+        }
+
+        let delay = backoff_delay(options.interval, failures, options.max_backoff)
+            + random_jitter(options.jitter);
+        if debug {
+            status!("Next check in {}s", delay.as_secs());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            signal = signals.next() => match signal {
+                Signal::Reload => {
+                    status!("Reloading config");
+                    if let Err(err) = CONFIG_SINGLETON.lock().await.reload() {
+                        eprintln!("{}, keeping the previous config", err);
+                    }
+                }
+                Signal::Shutdown => {
+                    status!("Shutting down");
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+/// The wait after `failures` failed checks in a row, doubling per failure up to `max`
+fn backoff_delay(interval: Duration, failures: u32, max: Duration) -> Duration {
+    if failures == 0 {
+        return interval;
+    }
+    let factor = 2u32.saturating_pow(failures.min(16));
+    interval.saturating_mul(factor).min(max.max(interval))
+}
+
+fn random_jitter(jitter: Duration) -> Duration {
+    Duration::from_millis(fastrand::u64(0..=jitter.as_millis() as u64))
+}
+
+#[cfg(unix)]
+struct Signals {
+    hangup: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> Result<Signals, DnsCliError> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Signals {
+            hangup: signal(SignalKind::hangup())?,
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    async fn next(&mut self) -> Signal {
+        tokio::select! {
+            _ = self.hangup.recv() => Signal::Reload,
+            _ = self.terminate.recv() => Signal::Shutdown,
+            _ = self.interrupt.recv() => Signal::Shutdown,
+        }
+    }
+}
+
+/// Only Ctrl-C is supported outside of unix
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> Result<Signals, DnsCliError> {
+        Ok(Signals)
+    }
+
+    async fn next(&mut self) -> Signal {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        Signal::Shutdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::backoff_delay;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_delay_doubles_up_to_max() {
+        let interval = Duration::from_secs(60);
+        let max = Duration::from_secs(600);
+        assert_eq!(backoff_delay(interval, 0, max), interval);
+        assert_eq!(backoff_delay(interval, 1, max), Duration::from_secs(120));
+        assert_eq!(backoff_delay(interval, 3, max), Duration::from_secs(480));
+        assert_eq!(backoff_delay(interval, 4, max), max);
+        assert_eq!(backoff_delay(interval, 100, max), max);
+    }
+
+    #[test]
+    fn backoff_delay_never_below_interval() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(
+            backoff_delay(interval, 2, Duration::from_secs(10)),
+            interval
+        );
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::config::{Record, RecordSettings, RecordType};
+use crate::dns_provider::DomainDetails;
+use crate::error::DnsCliError;
+use crate::ip_handler::IpVersion;
+use crate::record_content::{validate_record, RecordContent};
+
+/// A file with the zones and the records they should have, the records are created, updated
+/// and deleted by `apply` until the zones match it
+#[derive(Deserialize, Debug)]
+pub struct DesiredState {
+    pub zones: Vec<DesiredZone>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DesiredZone {
+    /// The name of the zone, like example.com
+    pub zone: String,
+    /// The account the zone is in, by default the first account that has the zone
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub records: Vec<DesiredRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DesiredRecord {
+    /// The name relative to the zone, `@` for the zone itself, or the full name
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// A fixed IP for A and AAAA records, left out they point to the IP of this server and are
+    /// tracked by `check`. The target, text or value of the other types.
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub priority: Option<u16>,
+    #[serde(default)]
+    pub weight: Option<u16>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub flags: Option<u8>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(flatten)]
+    pub settings: RecordSettings,
+}
+
+impl DesiredState {
+    /// Reads a YAML file, or a TOML file when the file name ends with `.toml`
+    pub fn load(path: &Path) -> Result<DesiredState, DnsCliError> {
+        let text = std::fs::read_to_string(path)?;
+        let invalid =
+            |e: String| DnsCliError::Config(format!("Failed to parse {}: {}", path.display(), e));
+        let state: DesiredState = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            _ => serde_yaml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        };
+        Ok(state)
+    }
+
+    /// The zones of the file with the account they are in, if given
+    pub fn zone_names(&self) -> Vec<(String, Option<String>)> {
+        self.zones
+            .iter()
+            .map(|zone| (zone.zone.clone(), zone.account.clone()))
+            .collect()
+    }
+}
+
+impl DesiredRecord {
+    /// The full name of the record in `zone`
+    pub fn full_name(&self, zone: &str) -> String {
+        let zone = zone.trim_end_matches('.');
+        if self.name == "@" {
+            return zone.to_string();
+        }
+        if let Some(name) = self.name.strip_suffix('.') {
+            return name.to_string();
+        }
+        if self.name == zone || self.name.ends_with(&format!(".{}", zone)) {
+            return self.name.clone();
+        }
+        format!("{}.{}", self.name, zone)
+    }
+
+    fn content_fields(&self) -> RecordContent {
+        RecordContent {
+            value: self.content.clone().unwrap_or_default(),
+            priority: self.priority,
+            weight: self.weight,
+            port: self.port,
+            flags: self.flags,
+            tag: self.tag.clone(),
+        }
+    }
+
+    /// Checks the record and turns it into the record to create, `ips` has the IPs of this
+    /// server for the A and AAAA records without content
+    pub fn plan(
+        &self,
+        zone: &str,
+        ips: &HashMap<IpVersion, String>,
+    ) -> Result<PlannedRecord, DnsCliError> {
+        let name = self.full_name(zone);
+        let content = self.content_fields();
+        self.settings.validate(self.record_type, &name)?;
+        let (content, value, dynamic) = match self.record_type.ip_version() {
+            Some(version) => {
+                if content
+                    != (RecordContent {
+                        value: content.value.clone(),
+                        ..Default::default()
+                    })
+                {
+                    return Err(DnsCliError::Config(format!(
+                        "Invalid {} record {}: only content can be set",
+                        self.record_type, name
+                    )));
+                }
+                match &self.content {
+                    Some(ip) => {
+                        let valid = match self.record_type {
+                            RecordType::A => ip.parse::<Ipv4Addr>().is_ok(),
+                            _ => ip.parse::<Ipv6Addr>().is_ok(),
+                        };
+                        if !valid {
+                            return Err(DnsCliError::Config(format!(
+                                "Invalid {} record {}: {:?} is not an {} address",
+                                self.record_type, name, ip, version
+                            )));
+                        }
+                        (None, ip.clone(), false)
+                    }
+                    None => {
+                        let ip = ips.get(&version).cloned().ok_or_else(|| {
+                            DnsCliError::IpDetection(format!(
+                                "The {} of this server is needed for {}",
+                                version, name
+                            ))
+                        })?;
+                        (None, ip, true)
+                    }
+                }
+            }
+            None => {
+                validate_record(self.record_type, &name, Some(&content))?;
+                let value = content.rdata(self.record_type);
+                (Some(content), value, false)
+            }
+        };
+        Ok(PlannedRecord {
+            record: Record {
+                id: "".to_string(),
+                name,
+                record_type: self.record_type,
+                content,
+                settings: self.settings.clone(),
+            },
+            value,
+            dynamic,
+        })
+    }
+}
+
+/// A declared record ready to be compared with the live records
+#[derive(Debug, Clone)]
+pub struct PlannedRecord {
+    pub record: Record,
+    /// The content in zone file notation, the IP for A and AAAA records
+    pub value: String,
+    /// Whether the record points to the IP of this server and is tracked by `check`
+    pub dynamic: bool,
+}
+
+/// What has to happen to a record for the zone to match the desired state
+#[derive(Debug)]
+pub enum Change {
+    Create(PlannedRecord),
+    Update {
+        live: DomainDetails,
+        desired: PlannedRecord,
+    },
+    Delete(DomainDetails),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Create(desired) => write!(
+                f,
+                "+ {} {} {}",
+                desired.record.name, desired.record.record_type, desired.value
+            ),
+            Change::Update { live, desired } => {
+                write!(
+                    f,
+                    "~ {} {}",
+                    desired.record.name, desired.record.record_type
+                )?;
+                if !same_value(desired.record.record_type, &live.content, &desired.value) {
+                    write!(f, " {} -> {}", live.content, desired.value)?;
+                }
+                let settings = &desired.record.settings;
+                if let Some(ttl) = settings.ttl.filter(|ttl| *ttl != live.ttl) {
+                    write!(f, ", ttl {} -> {}", live.ttl, ttl)?;
+                }
+                if let Some(proxied) = settings.proxied.filter(|p| *p != live.proxied) {
+                    write!(f, ", proxied {} -> {}", live.proxied, proxied)?;
+                }
+                Ok(())
+            }
+            Change::Delete(live) => {
+                write!(f, "- {} {} {}", live.name, live.record_type, live.content)
+            }
+        }
+    }
+}
+
+/// The result of comparing a zone with its desired records
+#[derive(Debug, Default)]
+pub struct ZoneDiff {
+    pub changes: Vec<Change>,
+    /// Records that point to the IP of this server and already match, with their live id
+    pub in_sync: Vec<PlannedRecord>,
+    /// Live records that are not declared and are left alone without `--prune`
+    pub undeclared: usize,
+}
+
+/// Compares the desired records of a zone with the live ones. Records are matched by name and
+/// type, a record whose content or settings differ is updated, a missing record is created.
+/// Live records of the types dns-cli manages that are not declared are deleted when `prune`
+/// is set, other types like NS and SOA are never touched.
+pub fn diff(desired: Vec<PlannedRecord>, live: Vec<DomainDetails>, prune: bool) -> ZoneDiff {
+    type Key = (String, String);
+    let key = |name: &str, record_type: &str| (name.to_lowercase(), record_type.to_string());
+    let mut desired_by_key: BTreeMap<Key, Vec<PlannedRecord>> = BTreeMap::new();
+    for record in desired {
+        desired_by_key
+            .entry(key(
+                &record.record.name,
+                &record.record.record_type.to_string(),
+            ))
+            .or_default()
+            .push(record);
+    }
+    let mut live_by_key: BTreeMap<Key, Vec<DomainDetails>> = BTreeMap::new();
+    for record in live {
+        if RecordType::from_api_name(&record.record_type).is_none() {
+            continue;
+        }
+        live_by_key
+            .entry(key(&record.name, &record.record_type))
+            .or_default()
+            .push(record);
+    }
+
+    let mut result = ZoneDiff::default();
+    for (key, desired) in desired_by_key {
+        let mut live = live_by_key.remove(&key).unwrap_or_default();
+        let mut unmatched = vec![];
+        for mut record in desired {
+            let found = live
+                .iter()
+                .position(|l| same_value(record.record.record_type, &l.content, &record.value));
+            match found {
+                Some(index) => {
+                    let live_record = live.remove(index);
+                    if settings_differ(&record.record.settings, &live_record) {
+                        result.changes.push(Change::Update {
+                            live: live_record,
+                            desired: record,
+                        });
+                    } else {
+                        record.record.id = live_record.id;
+                        result.in_sync.push(record);
+                    }
+                }
+                None => unmatched.push(record),
+            }
+        }
+        let mut live = live.into_iter();
+        for record in unmatched {
+            match live.next() {
+                Some(live_record) => result.changes.push(Change::Update {
+                    live: live_record,
+                    desired: record,
+                }),
+                None => result.changes.push(Change::Create(record)),
+            }
+        }
+        let leftover: Vec<DomainDetails> = live.collect();
+        if prune {
+            result
+                .changes
+                .extend(leftover.into_iter().map(Change::Delete));
+        } else {
+            result.undeclared += leftover.len();
+        }
+    }
+    for (_, leftover) in live_by_key {
+        if prune {
+            result
+                .changes
+                .extend(leftover.into_iter().map(Change::Delete));
+        } else {
+            result.undeclared += leftover.len();
+        }
+    }
+    result
+}
+
+fn settings_differ(desired: &RecordSettings, live: &DomainDetails) -> bool {
+    desired.ttl.is_some_and(|ttl| ttl != live.ttl)
+        || desired
+            .proxied
+            .is_some_and(|proxied| proxied != live.proxied)
+}
+
+/// Compares contents in zone file notation, host names without case or trailing dot and
+/// TXT records with or without quotes
+pub fn same_value(record_type: RecordType, live: &str, desired: &str) -> bool {
+    normalize(record_type, live) == normalize(record_type, desired)
+}
+
+fn normalize(record_type: RecordType, value: &str) -> String {
+    match record_type {
+        RecordType::TXT => {
+            let value = value.trim();
+            match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => value.to_string(),
+            }
+        }
+        RecordType::CAA => value.split_whitespace().collect::<Vec<_>>().join(" "),
+        _ => value
+            .split_whitespace()
+            .map(|part| part.trim_end_matches('.').to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change, DesiredState, PlannedRecord};
+    use crate::config::RecordType;
+    use crate::ip_handler::IpVersion;
+    use crate::test_fixtures::{live, record};
+    use std::collections::HashMap;
+
+    fn planned(name: &str, record_type: RecordType, value: &str) -> PlannedRecord {
+        PlannedRecord {
+            record: record(name, record_type),
+            value: value.to_string(),
+            dynamic: false,
+        }
+    }
+
+    #[test]
+    fn diff_creates_updates_and_prunes() {
+        let desired = vec![
+            planned("www.example.com", RecordType::CNAME, "example.com"),
+            planned("example.com", RecordType::TXT, "\"v=spf1 -all\""),
+            planned("new.example.com", RecordType::A, "192.0.2.1"),
+        ];
+        let records = vec![
+            live("WWW.example.com", "CNAME", "example.com."),
+            live("example.com", "TXT", "v=spf1 mx -all"),
+            live("old.example.com", "A", "192.0.2.9"),
+            live("example.com", "NS", "ns1.example.net"),
+        ];
+
+        let result = diff(desired.clone(), records.clone(), false);
+        assert_eq!(result.undeclared, 1);
+        let changes: Vec<String> = result.changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "~ example.com TXT v=spf1 mx -all -> \"v=spf1 -all\"",
+                "+ new.example.com A 192.0.2.1",
+            ]
+        );
+
+        let result = diff(desired, records, true);
+        assert_eq!(result.undeclared, 0);
+        assert!(
+            matches!(result.changes.last(), Some(Change::Delete(live)) if live.name == "old.example.com")
+        );
+        assert_eq!(result.changes.len(), 3);
+    }
+
+    #[test]
+    fn diff_updates_settings_that_differ() {
+        let mut record = planned("ssh.example.com", RecordType::A, "192.0.2.1");
+        record.record.settings.ttl = Some(300);
+        let result = diff(
+            vec![record],
+            vec![live("ssh.example.com", "A", "192.0.2.1")],
+            false,
+        );
+        assert_eq!(
+            result.changes[0].to_string(),
+            "~ ssh.example.com A, ttl 1 -> 300"
+        );
+    }
+
+    #[test]
+    fn yaml_and_toml_files_plan_the_same_records() {
+        let yaml = r#"
+zones:
+  - zone: example.com
+    records:
+      - name: "@"
+        type: MX
+        content: mail.example.com
+        priority: 10
+      - name: home
+        type: A
+        proxied: false
+"#;
+        let toml = r#"
+[[zones]]
+zone = "example.com"
+
+[[zones.records]]
+name = "@"
+type = "MX"
+content = "mail.example.com"
+priority = 10
+
+[[zones.records]]
+name = "home"
+type = "A"
+proxied = false
+"#;
+        let ips = HashMap::from([(IpVersion::V4, "192.0.2.1".to_string())]);
+        for state in [
+            serde_yaml::from_str::<DesiredState>(yaml).unwrap(),
+            toml::from_str::<DesiredState>(toml).unwrap(),
+        ] {
+            let zone = &state.zones[0];
+            let mx = zone.records[0].plan(&zone.zone, &ips).unwrap();
+            assert_eq!(mx.record.name, "example.com");
+            assert_eq!(mx.value, "10 mail.example.com");
+            let home = zone.records[1].plan(&zone.zone, &ips).unwrap();
+            assert_eq!(home.record.name, "home.example.com");
+            assert_eq!(home.value, "192.0.2.1");
+            assert!(home.dynamic);
+            assert_eq!(home.record.settings.proxied, Some(false));
+        }
+    }
+}
@@ -1,28 +1,57 @@
-use crate::webhook_notifier::WebhookNotifier;
+use crate::error::DnsCliError;
+use crate::output::status;
+use crate::secret::Secret;
+use crate::webhook_notifier::{default_events, EventKind, NotifyEvent, WebhookNotifier};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DiscordWebhook {
-    url: String,
+    /// The webhook URL holds its token, so it can be a secret reference
+    url: Secret,
+    #[serde(default = "default_events")]
+    events: Vec<EventKind>,
 }
 
 impl DiscordWebhook {
-    pub fn new(url: String) -> DiscordWebhook {
-        DiscordWebhook { url }
+    pub fn new(url: String, events: Vec<EventKind>) -> DiscordWebhook {
+        DiscordWebhook {
+            url: Secret::new(url),
+            events,
+        }
+    }
+
+    /// The URL as the config has it
+    pub fn url(&self) -> &str {
+        self.url.written()
+    }
+
+    pub fn resolve_secrets(&mut self) -> Result<(), DnsCliError> {
+        self.url.resolve("the url of a Discord webhook")
+    }
+
+    /// The webhook with the token of its URL hidden
+    pub fn redacted(&self) -> DiscordWebhook {
+        DiscordWebhook {
+            url: Secret::new(self.url.redacted_url()),
+            ..self.clone()
+        }
+    }
+
+    pub fn events(&self) -> &[EventKind] {
+        &self.events
     }
 }
 
 impl WebhookNotifier for DiscordWebhook {
-    async fn change_ip(&self, from: &String, to: &String) {
-        println!("DiscordWebhook::change_ip from: {}, to: {}", from, to);
+    async fn notify(&self, event: &NotifyEvent) {
+        let message = event.message();
+        status!("DiscordWebhook::notify {}", message);
         let client = Client::new();
         let _ = client
-            .post(&self.url)
-            .json(&json!({
-                "content": format!("IP changed from {} to {}", from, to)
-            }))
+            .post(self.url.expose())
+            .json(&json!({ "content": message }))
             .send()
             .await;
     }
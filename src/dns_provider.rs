@@ -1,18 +1,60 @@
-use crate::config::Record;
+use crate::cloudflare_provider::CloudflareProvider;
+use crate::config::{Domain, ProviderAccount, ProviderKind, Record};
+use crate::error::DnsCliError;
+use crate::godaddy_api::GoDaddyAPI;
+use crate::interaction;
+use crate::ip_handler::IpVersion;
+use crate::output::status;
+use serde::Serialize;
 
 pub trait DnsProvider {
-    async fn set_sub_domain(&self, record: &Record, zone_id: String) -> String;
+    /// The config account this provider manages
+    fn account(&self) -> &ProviderAccount;
+    fn account_mut(&mut self) -> &mut ProviderAccount;
 
-    async fn remove_sub_domain(&self, record: &Record, zone_id: String);
+    /// Creates the record pointing to the current IP and returns its id
+    async fn set_sub_domain(&self, record: &Record, zone_id: String)
+        -> Result<String, DnsCliError>;
 
-    async fn change_ip(&self, ip: &str);
-    async fn import(&mut self);
+    /// Creates the record as it is, A and AAAA records point to `ip`, and returns its id
+    async fn create_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<String, DnsCliError>;
 
-    async fn get_domain_details(&self, prefix: &str) -> Result<DomainDetails, Box<dyn std::error::Error>>;
+    async fn remove_sub_domain(&self, record: &Record, zone_id: String) -> Result<(), DnsCliError>;
+
+    /// Checks that the provider takes the record, like its TTL, before anything is sent
+    fn check_record(&self, record: &Record, zone_id: &str) -> Result<(), DnsCliError>;
+
+    /// Points a single tracked record to the new IP
+    async fn update_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<(), DnsCliError>;
+    /// The content the record currently has at the provider
+    async fn get_record_content(
+        &self,
+        record: &Record,
+        zone_id: &str,
+    ) -> Result<String, DnsCliError>;
+
+    async fn import(&mut self) -> Result<(), DnsCliError>;
+
+    async fn get_domain_details(&self, prefix: &str) -> Result<DomainDetails, DnsCliError>;
+
+    /// Every record in the zone as the provider has it, tracked or not
+    async fn list_records(&self, zone_id: &str) -> Result<Vec<DomainDetails>, DnsCliError>;
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct DomainDetails {
+    /// The id the record is tracked by in the config
+    pub id: String,
     pub name: String,
     pub record_type: String,
     pub content: String,
@@ -20,3 +62,201 @@ pub struct DomainDetails {
     pub ttl: u32,
     pub modified_on: Option<String>,
 }
+
+/// Merges the records found pointing to this server into the tracked records of a zone.
+/// Records already tracked keep their settings and content, records of IP versions not in
+/// `current_ips` are kept as they are, and tracked records no longer found are pruned when
+/// the user agrees.
+pub fn merge_imported(
+    zone: &mut Domain,
+    imported: Vec<Record>,
+    current_ips: &[(IpVersion, String)],
+) -> Result<(), DnsCliError> {
+    let (mut merged, checked): (Vec<Record>, Vec<Record>) =
+        zone.records.drain(..).partition(|record| {
+            !current_ips
+                .iter()
+                .any(|(version, _)| record.record_type.ip_version() == Some(*version))
+        });
+    for record in imported {
+        match checked.iter().find(|r| r.id == record.id) {
+            Some(existing) => merged.push(existing.clone()),
+            None => {
+                status!("Importing {} ({})", record.name, record.record_type);
+                merged.push(record);
+            }
+        }
+    }
+    for existing in checked {
+        if merged.iter().any(|r| r.id == existing.id) {
+            continue;
+        }
+        if interaction::prune_missing(&existing.name)? {
+            status!("No longer tracking {}", existing.name);
+        } else {
+            merged.push(existing);
+        }
+    }
+    zone.records = merged;
+    Ok(())
+}
+
+/// Any of the supported providers, used when commands run against accounts of different providers
+pub enum AnyProvider {
+    Cloudflare(CloudflareProvider),
+    GoDaddy(GoDaddyAPI),
+}
+
+impl AnyProvider {
+    pub async fn new(account: ProviderAccount, debug: bool) -> Result<AnyProvider, DnsCliError> {
+        Ok(match account.kind() {
+            ProviderKind::Cloudflare => {
+                AnyProvider::Cloudflare(CloudflareProvider::new(account).await?)
+            }
+            ProviderKind::GoDaddy => AnyProvider::GoDaddy(GoDaddyAPI::new(account, debug).await?),
+        })
+    }
+}
+
+impl DnsProvider for AnyProvider {
+    fn account(&self) -> &ProviderAccount {
+        match self {
+            AnyProvider::Cloudflare(api) => api.account(),
+            AnyProvider::GoDaddy(api) => api.account(),
+        }
+    }
+
+    fn account_mut(&mut self) -> &mut ProviderAccount {
+        match self {
+            AnyProvider::Cloudflare(api) => api.account_mut(),
+            AnyProvider::GoDaddy(api) => api.account_mut(),
+        }
+    }
+
+    async fn set_sub_domain(
+        &self,
+        record: &Record,
+        zone_id: String,
+    ) -> Result<String, DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.set_sub_domain(record, zone_id).await,
+            AnyProvider::GoDaddy(api) => api.set_sub_domain(record, zone_id).await,
+        }
+    }
+
+    async fn create_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<String, DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.create_record(record, zone_id, ip).await,
+            AnyProvider::GoDaddy(api) => api.create_record(record, zone_id, ip).await,
+        }
+    }
+
+    async fn remove_sub_domain(&self, record: &Record, zone_id: String) -> Result<(), DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.remove_sub_domain(record, zone_id).await,
+            AnyProvider::GoDaddy(api) => api.remove_sub_domain(record, zone_id).await,
+        }
+    }
+
+    fn check_record(&self, record: &Record, zone_id: &str) -> Result<(), DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.check_record(record, zone_id),
+            AnyProvider::GoDaddy(api) => api.check_record(record, zone_id),
+        }
+    }
+
+    async fn update_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<(), DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.update_record(record, zone_id, ip).await,
+            AnyProvider::GoDaddy(api) => api.update_record(record, zone_id, ip).await,
+        }
+    }
+
+    async fn get_record_content(
+        &self,
+        record: &Record,
+        zone_id: &str,
+    ) -> Result<String, DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.get_record_content(record, zone_id).await,
+            AnyProvider::GoDaddy(api) => api.get_record_content(record, zone_id).await,
+        }
+    }
+
+    async fn import(&mut self) -> Result<(), DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.import().await,
+            AnyProvider::GoDaddy(api) => api.import().await,
+        }
+    }
+
+    async fn get_domain_details(&self, prefix: &str) -> Result<DomainDetails, DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.get_domain_details(prefix).await,
+            AnyProvider::GoDaddy(api) => api.get_domain_details(prefix).await,
+        }
+    }
+
+    async fn list_records(&self, zone_id: &str) -> Result<Vec<DomainDetails>, DnsCliError> {
+        match self {
+            AnyProvider::Cloudflare(api) => api.list_records(zone_id).await,
+            AnyProvider::GoDaddy(api) => api.list_records(zone_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_imported;
+    use crate::config::{Domain, Record, RecordSettings, RecordType};
+    use crate::ip_handler::IpVersion;
+    use crate::test_fixtures::record;
+
+    #[test]
+    fn imported_records_keep_the_settings_they_are_tracked_with() {
+        let settings = RecordSettings {
+            ttl: Some(3600),
+            comment: Some("ssh".to_string()),
+            ..Default::default()
+        };
+        let mut zone = Domain {
+            domain: "example.com".to_string(),
+            records: vec![
+                Record {
+                    id: "ssh".to_string(),
+                    settings: settings.clone(),
+                    ..record("ssh.example.com", RecordType::A)
+                },
+                Record {
+                    id: "v6".to_string(),
+                    ..record("v6.example.com", RecordType::AAAA)
+                },
+            ],
+        };
+        let imported = vec![
+            Record {
+                id: "ssh".to_string(),
+                ..record("ssh.example.com", RecordType::A)
+            },
+            Record {
+                id: "new".to_string(),
+                ..record("new.example.com", RecordType::A)
+            },
+        ];
+        let current_ips = [(IpVersion::V4, "192.0.2.1".to_string())];
+        merge_imported(&mut zone, imported, &current_ips).unwrap();
+        let ids: Vec<&str> = zone.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["v6", "ssh", "new"]);
+        assert_eq!(zone.records[1].settings, settings);
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Every failure the CLI can run into, each class exits with its own code
+#[derive(Debug)]
+pub enum DnsCliError {
+    /// The config file is missing or invalid, or a value in it is wrong
+    Config(String),
+    /// A request could not be sent or its response could not be read
+    Http(reqwest::Error),
+    /// The DNS provider rejected a request or answered with something unexpected
+    Api(String),
+    /// The public IP could not be detected
+    IpDetection(String),
+    /// A local file could not be read or written
+    Io(std::io::Error),
+    /// Some records could not be updated during a check, holds how many
+    RecordsFailed(usize),
+}
+
+impl DnsCliError {
+    /// The process exit code for this error, 1 and 2 are left to panics and clap usage errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DnsCliError::Config(_) => 3,
+            DnsCliError::Http(_) => 4,
+            DnsCliError::Api(_) => 5,
+            DnsCliError::IpDetection(_) => 6,
+            DnsCliError::Io(_) => 7,
+            DnsCliError::RecordsFailed(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for DnsCliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsCliError::Config(message) => write!(f, "Config error: {}", message),
+            DnsCliError::Http(err) => write!(f, "HTTP error: {}", err),
+            DnsCliError::Api(message) => write!(f, "API error: {}", message),
+            DnsCliError::IpDetection(message) => write!(f, "Could not detect IP: {}", message),
+            DnsCliError::Io(err) => write!(f, "IO error: {}", err),
+            DnsCliError::RecordsFailed(count) => write!(
+                f,
+                "{} record(s) could not be updated, they will be retried on the next check",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DnsCliError {}
+
+impl From<reqwest::Error> for DnsCliError {
+    fn from(err: reqwest::Error) -> Self {
+        DnsCliError::Http(err)
+    }
+}
+
+impl From<std::io::Error> for DnsCliError {
+    fn from(err: std::io::Error) -> Self {
+        DnsCliError::Io(err)
+    }
+}
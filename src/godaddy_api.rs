@@ -1,95 +1,751 @@
-use std::error::Error;
+use crate::config::{
+    find_zone_id, Domain, ProviderAccount, ProviderCredentials, Record, RecordSettings, RecordType,
+    CONFIG_SINGLETON,
+};
+use crate::dns_provider::{merge_imported, DnsProvider, DomainDetails};
+use crate::error::DnsCliError;
+use crate::interaction;
+use crate::ip_handler::{get_current_ip, IpVersion};
+use crate::output::status;
+use crate::secret::Secret;
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::dns_provider::DnsProvider;
-use reqwest::Client;
-use serde::Serialize;
-use serde_json::json;
+const GODADDY_API_URL: &str = "https://api.godaddy.com/v1";
+/// GoDaddy's default and lowest TTL
+const DEFAULT_TTL: u32 = 600;
 
-use crate::config::Record;
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoDaddyDomain {
+    domain: String,
+    status: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GoDaddyRecord {
+    data: String,
+    name: String,
+    ttl: u32,
+    #[serde(rename = "type")]
+    type_field: String,
+    #[serde(default)]
+    priority: Option<u16>,
+    #[serde(default)]
+    weight: Option<u16>,
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    service: Option<String>,
+    #[serde(default)]
+    protocol: Option<String>,
+}
+
+impl GoDaddyRecord {
+    /// The record with its full name and its content in zone file notation
+    fn details(&self, domain: &str) -> DomainDetails {
+        let relative = match (&self.service, &self.protocol) {
+            (Some(service), Some(protocol)) if self.name == "@" => {
+                format!("{}.{}", service, protocol)
+            }
+            (Some(service), Some(protocol)) => format!("{}.{}.{}", service, protocol, self.name),
+            _ => self.name.clone(),
+        };
+        let content = match self.type_field.as_str() {
+            "MX" => format!("{} {}", self.priority.unwrap_or_default(), self.data),
+            "SRV" => format!(
+                "{} {} {} {}",
+                self.priority.unwrap_or_default(),
+                self.weight.unwrap_or_default(),
+                self.port.unwrap_or_default(),
+                self.data
+            ),
+            _ => self.data.clone(),
+        };
+        DomainDetails {
+            // The same format as record_id
+            id: format!("{}/{}/{}", domain, self.type_field, relative),
+            name: full_name(&relative, domain),
+            record_type: self.type_field.clone(),
+            content,
+            proxied: false,
+            ttl: self.ttl,
+            modified_on: None,
+        }
+    }
+
+    /// The record as it is sent back when its set is replaced
+    fn entry(&self) -> Value {
+        let mut entry = json!({"data": self.data, "ttl": self.ttl});
+        for (key, value) in [
+            ("priority", self.priority.map(|v| json!(v))),
+            ("weight", self.weight.map(|v| json!(v))),
+            ("port", self.port.map(|v| json!(v))),
+            ("service", self.service.as_ref().map(|v| json!(v))),
+            ("protocol", self.protocol.as_ref().map(|v| json!(v))),
+        ] {
+            if let Some(value) = value {
+                entry[key] = value;
+            }
+        }
+        entry
+    }
+
+    fn is_srv(&self, service: &str, protocol: &str) -> bool {
+        self.service.as_deref() == Some(service) && self.protocol.as_deref() == Some(protocol)
+    }
+}
+
+/// Records fetched per request when listing a domain
+const PAGE_SIZE: usize = 500;
 
 pub struct GoDaddyAPI {
+    account: ProviderAccount,
     api_key: String,
     secret: String,
-    domain: String,
-    ip: String,
     client: Client,
     debug: bool,
 }
 impl GoDaddyAPI {
-    pub fn new(
-        api_key: String,
-        secret: String,
-        domain: String,
-        ip: String,
-        debug: bool,
-    ) -> GoDaddyAPI {
-        GoDaddyAPI {
+    pub async fn new(mut account: ProviderAccount, debug: bool) -> Result<GoDaddyAPI, DnsCliError> {
+        let ProviderCredentials::GoDaddy { api_key, secret } = account.credentials.clone() else {
+            return Err(DnsCliError::Config(format!(
+                "Account {} is not a GoDaddy account",
+                account.name
+            )));
+        };
+        let (api_key, secret) = if api_key.is_empty() || secret.is_empty() {
+            let hint = format!(
+                "set api_key and secret of account {} in the config",
+                account.name
+            );
+            let api_key = interaction::text(
+                &format!("Please enter your GoDaddy api key for {}", account.name),
+                &hint,
+            )?;
+            let secret = interaction::text(
+                &format!("Please enter your GoDaddy api secret for {}", account.name),
+                &hint,
+            )?;
+            account.credentials = ProviderCredentials::GoDaddy {
+                api_key: Secret::new(api_key.clone()),
+                secret: Secret::new(secret.clone()),
+            };
+            CONFIG_SINGLETON
+                .lock()
+                .await
+                .save_account(account.clone())?;
+            (api_key, secret)
+        } else {
+            (api_key.expose().to_string(), secret.expose().to_string())
+        };
+        Ok(GoDaddyAPI {
+            account,
             api_key,
             secret,
-            domain,
-            ip,
             client: Client::new(),
             debug,
-        }
+        })
     }
 
     async fn send_request<T>(
-        client: &Client,
-        url: String,
-        body: T,
-        api_key: &String,
-        secret: &String,
-    ) -> Result<String, Box<dyn Error>>
+        &self,
+        method: Method,
+        path: String,
+        body: Option<T>,
+    ) -> Result<String, DnsCliError>
     where
         T: Serialize,
     {
-        let resp = client
-            .put(&url)
-            .header("authorization", format!("sso-key {}:{}", api_key, secret))
-            .json(&body)
-            .send()
-            .await?
-            .text()
+        let url = format!("{}{}", GODADDY_API_URL, path);
+        let mut request = self.client.request(method, &url).header(
+            "authorization",
+            format!("sso-key {}:{}", self.api_key, self.secret),
+        );
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let resp = request.send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if self.debug {
+            status!("GoDaddy {} responded {}: {}", url, status, text);
+        }
+        if !status.is_success() {
+            return Err(DnsCliError::Api(format!(
+                "GoDaddy returned HTTP {}: {}",
+                status, text
+            )));
+        }
+        Ok(text)
+    }
+
+    async fn get_records(
+        &self,
+        domain: &str,
+        record_type: &str,
+        name: Option<&str>,
+    ) -> Result<Vec<GoDaddyRecord>, DnsCliError> {
+        let path = match name {
+            Some(name) => format!("/domains/{}/records/{}/{}", domain, record_type, name),
+            None => format!("/domains/{}/records/{}", domain, record_type),
+        };
+        let text = self.send_request::<()>(Method::GET, path, None).await?;
+        parse_response(&text)
+    }
+
+    /// Creates or updates the entry of `record` in its set, keeping the other entries
+    async fn put_record(
+        &self,
+        record: &Record,
+        domain: &str,
+        ip: &str,
+        create: bool,
+    ) -> Result<(), DnsCliError> {
+        let entry = record_entry(record, domain, ip)?;
+        let change = match create {
+            true => SetChange::Create(entry),
+            false => SetChange::Update(entry),
+        };
+        let live = self.live_set(record, domain).await?;
+        let body = record_set(record, domain, ip, live, change)?;
+        self.send_request(Method::PUT, record_path(record, domain)?, Some(body))
             .await?;
-        Ok(resp)
+        Ok(())
+    }
+
+    /// The set `record` is kept in, with the entries of other SRV services at its host
+    async fn live_set(
+        &self,
+        record: &Record,
+        domain: &str,
+    ) -> Result<Vec<GoDaddyRecord>, DnsCliError> {
+        let path = record_path(record, domain)?;
+        let text = self.send_request::<()>(Method::GET, path, None).await?;
+        parse_response(&text)
+    }
+
+    /// The live records that are `record`, SRV records are picked out of the set of their host
+    async fn live_records(
+        &self,
+        record: &Record,
+        domain: &str,
+    ) -> Result<Vec<GoDaddyRecord>, DnsCliError> {
+        let mut records = self.live_set(record, domain).await?;
+        if record.record_type == RecordType::SRV {
+            let (service, protocol) = srv_service(record, domain)?;
+            records.retain(|live| live.is_srv(&service, &protocol));
+        }
+        Ok(records)
+    }
+
+    async fn sync_zones(&mut self) -> Result<(), DnsCliError> {
+        let text = self
+            .send_request::<()>(Method::GET, "/domains".to_string(), None)
+            .await?;
+        let domains: Vec<GoDaddyDomain> = parse_response(&text)?;
+        for domain in domains.iter().filter(|d| d.status == "ACTIVE") {
+            if self.account.domains.contains_key(&domain.domain) {
+                continue;
+            }
+            if interaction::add_zone(&domain.domain)? {
+                status!("Added domain: {}", domain.domain);
+                self.account.domains.insert(
+                    domain.domain.clone(),
+                    Domain {
+                        domain: domain.domain.clone(),
+                        records: vec![],
+                    },
+                );
+            }
+        }
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(self.account.clone())
+    }
+}
+
+fn parse_response<R: DeserializeOwned>(text: &str) -> Result<R, DnsCliError> {
+    serde_json::from_str(text).map_err(|e| {
+        DnsCliError::Api(format!(
+            "Failed to parse response: {}\nResponse: {}",
+            e, text
+        ))
+    })
+}
+
+/// GoDaddy names records relative to the domain, `@` being the domain itself
+fn relative_name(name: &str, domain: &str) -> String {
+    if name == domain {
+        return "@".to_string();
+    }
+    name.strip_suffix(&format!(".{}", domain))
+        .unwrap_or(name)
+        .to_string()
+}
+
+fn full_name(name: &str, domain: &str) -> String {
+    if name == "@" {
+        domain.to_string()
+    } else {
+        format!("{}.{}", name, domain)
+    }
+}
+
+/// Splits a relative SRV name like `_sip._tcp.office` into service, protocol and host
+fn split_srv_name(name: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = name.splitn(3, '.');
+    let service = parts.next()?;
+    let protocol = parts.next()?;
+    let host = parts.next().unwrap_or("@");
+    Some((service, protocol, host))
+}
+
+/// The service and protocol of an SRV record, like `_sip` and `_tcp`
+fn srv_service(record: &Record, domain: &str) -> Result<(String, String), DnsCliError> {
+    let name = relative_name(&record.name, domain);
+    split_srv_name(&name)
+        .map(|(service, protocol, _)| (service.to_string(), protocol.to_string()))
+        .ok_or_else(|| DnsCliError::Config(format!("{} is not an SRV name", record.name)))
+}
+
+/// What happens to the entry of a record in the set it is kept in
+enum SetChange {
+    Create(Value),
+    Update(Value),
+    Remove,
+}
+
+/// The set a record is kept in with its entry created, replaced or removed. GoDaddy replaces or
+/// deletes a whole set at once, the records of a name and type, or for SRV records the records
+/// of a host, so the other entries are sent back as they are. The entry of the record is the
+/// one with the same content, `ip` for A and AAAA records, or else the only entry with the same
+/// name. A create adds a new entry unless the record follows the IP of this server.
+fn record_set(
+    record: &Record,
+    domain: &str,
+    ip: &str,
+    live: Vec<GoDaddyRecord>,
+    change: SetChange,
+) -> Result<Vec<Value>, DnsCliError> {
+    let srv = match record.record_type {
+        RecordType::SRV => Some(srv_service(record, domain)?),
+        _ => None,
+    };
+    let target = match &record.content {
+        Some(content) => content.value.as_str(),
+        None => ip,
+    };
+    // Host names are compared without case and trailing dot, texts as they are
+    let normalize = |data: &str| match record.record_type {
+        RecordType::TXT => data.to_string(),
+        _ => data.trim_end_matches('.').to_lowercase(),
+    };
+    let same_name: Vec<usize> = (0..live.len())
+        .filter(|&i| match &srv {
+            Some((service, protocol)) => live[i].is_srv(service, protocol),
+            None => true,
+        })
+        .collect();
+    let same_content = same_name
+        .iter()
+        .copied()
+        .find(|&i| !target.is_empty() && normalize(&live[i].data) == normalize(target));
+    let only = || {
+        match same_name.as_slice() {
+        [] => Ok(None),
+        [i] => Ok(Some(*i)),
+        _ => Err(DnsCliError::Config(format!(
+            "{} has several {} records at GoDaddy and none of them is {}, can't tell which to change",
+            record.name, record.record_type, target
+        ))),
+    }
+    };
+    let (replaced, entry) = match change {
+        SetChange::Create(entry) if record.content.is_some() => (same_content, Some(entry)),
+        SetChange::Create(entry) | SetChange::Update(entry) => (
+            same_content.map_or_else(only, |i| Ok(Some(i)))?,
+            Some(entry),
+        ),
+        SetChange::Remove => (same_content.map_or_else(only, |i| Ok(Some(i)))?, None),
+    };
+    let mut set: Vec<Value> = live
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != replaced)
+        .map(|(_, live)| live.entry())
+        .collect();
+    set.extend(entry);
+    Ok(set)
+}
+
+/// The path of a record set, SRV records are addressed by their host without service and protocol
+fn record_path(record: &Record, domain: &str) -> Result<String, DnsCliError> {
+    let name = relative_name(&record.name, domain);
+    let name = match record.record_type {
+        RecordType::SRV => split_srv_name(&name)
+            .map(|(_, _, host)| host.to_string())
+            .ok_or_else(|| DnsCliError::Config(format!("{} is not an SRV name", record.name)))?,
+        _ => name,
+    };
+    Ok(format!(
+        "/domains/{}/records/{}/{}",
+        domain, record.record_type, name
+    ))
+}
+
+/// The record as GoDaddy takes it, A and AAAA records point to `ip`. GoDaddy replaces the whole
+/// record set, so the TTL of the config is sent on every update.
+fn record_entry(record: &Record, domain: &str, ip: &str) -> Result<Value, DnsCliError> {
+    let settings = &record.settings;
+    let unsupported = |setting: &str| {
+        DnsCliError::Config(format!(
+            "Can't set {} on {}, GoDaddy records have no {}",
+            setting, record.name, setting
+        ))
+    };
+    if settings.proxied == Some(true) {
+        return Err(unsupported("proxied"));
+    }
+    if settings.comment.is_some() {
+        return Err(unsupported("comment"));
     }
+    if !settings.tags.is_empty() {
+        return Err(unsupported("tags"));
+    }
+    let ttl = settings.ttl.unwrap_or(DEFAULT_TTL);
+    if ttl < DEFAULT_TTL {
+        return Err(DnsCliError::Config(format!(
+            "Invalid TTL {} for {}, GoDaddy takes at least {} seconds",
+            ttl, record.name, DEFAULT_TTL
+        )));
+    }
+    let Some(content) = &record.content else {
+        return Ok(json!({"data": ip, "ttl": ttl}));
+    };
+    let mut entry = json!({"data": content.value, "ttl": ttl});
+    match record.record_type {
+        RecordType::MX => entry["priority"] = json!(content.priority),
+        RecordType::SRV => {
+            let name = relative_name(&record.name, domain);
+            let (service, protocol, _) = split_srv_name(&name).ok_or_else(|| {
+                DnsCliError::Config(format!("{} is not an SRV name", record.name))
+            })?;
+            entry["service"] = json!(service);
+            entry["protocol"] = json!(protocol);
+            entry["priority"] = json!(content.priority);
+            entry["weight"] = json!(content.weight);
+            entry["port"] = json!(content.port);
+        }
+        RecordType::CAA => {
+            return Err(DnsCliError::Config(format!(
+                "Can't create {}, the GoDaddy API does not support CAA records",
+                record.name
+            )))
+        }
+        _ => {}
+    }
+    Ok(entry)
+}
+
+/// GoDaddy has no record ids, the domain, type and relative name identify a record set
+fn record_id(record_type: RecordType, name: &str, domain: &str) -> String {
+    format!("{}/{}/{}", domain, record_type, relative_name(name, domain))
 }
 
 impl DnsProvider for GoDaddyAPI {
-    async fn set_sub_domain(&self, record: &Record, zone_id: String) -> String {
-        let url = format!(
-            "https://api.godaddy.com/v1/domains/{}/records/{}/{}",
-            self.domain,
-            record.record_type.to_string(),
-            record.name
-        );
+    fn account(&self) -> &ProviderAccount {
+        &self.account
+    }
 
-        let body =
-            vec![json!({"data": self.ip, "ttl": 600, "type": record.record_type.to_string()})];
-        let resp = Self::send_request(&self.client, url, body, &self.api_key, &self.secret).await;
-        if let Err(err) = resp {
-            panic!(
-                "Something went wrong, please create a bug report with this error: {:?}",
-                err
-            );
-        } else if self.debug {
-            println!("Ok, Response: {:?}", resp);
-            return "".to_string();
+    fn account_mut(&mut self) -> &mut ProviderAccount {
+        &mut self.account
+    }
+
+    async fn set_sub_domain(
+        &self,
+        record: &Record,
+        zone_id: String,
+    ) -> Result<String, DnsCliError> {
+        let ip = match record.record_type.ip_version() {
+            Some(version) => get_current_ip(version).await?,
+            None => String::new(),
+        };
+        let id = self.create_record(record, &zone_id, &ip).await?;
+        status!("The subdomain {} has been registered", record.name);
+        Ok(id)
+    }
+
+    async fn create_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<String, DnsCliError> {
+        self.put_record(record, zone_id, ip, true).await?;
+        Ok(record_id(record.record_type, &record.name, zone_id))
+    }
+
+    async fn remove_sub_domain(&self, record: &Record, zone_id: String) -> Result<(), DnsCliError> {
+        let path = record_path(record, &zone_id)?;
+        let live = self.live_set(record, &zone_id).await?;
+        let rest = record_set(record, &zone_id, "", live, SetChange::Remove)?;
+        if rest.is_empty() {
+            self.send_request::<()>(Method::DELETE, path, None).await?;
         } else {
-            println!("The subdomain {} has been registered", record.name);
-            return "".to_string();
+            self.send_request(Method::PUT, path, Some(rest)).await?;
         }
+        status!("Removed {}", record.name);
+        Ok(())
     }
 
-    async fn import(&mut self) {
-        todo!()
+    fn check_record(&self, record: &Record, zone_id: &str) -> Result<(), DnsCliError> {
+        record_entry(record, zone_id, "").map(|_| ())
     }
 
-    async fn remove_sub_domain(&self, record: &Record, zone_id: String) {
-        todo!()
+    async fn update_record(
+        &self,
+        record: &Record,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<(), DnsCliError> {
+        self.put_record(record, zone_id, ip, false).await?;
+        status!("Updated {} to {}", record.name, record.display_content(ip));
+        Ok(())
     }
 
-    async fn change_ip(&self, ip: &str) {
-        todo!()
+    async fn get_record_content(
+        &self,
+        record: &Record,
+        zone_id: &str,
+    ) -> Result<String, DnsCliError> {
+        let records = self.live_records(record, zone_id).await?;
+        records
+            .into_iter()
+            .next()
+            .map(|live| live.data)
+            .ok_or_else(|| DnsCliError::Api(format!("{} does not exist at GoDaddy", record.name)))
+    }
+
+    async fn import(&mut self) -> Result<(), DnsCliError> {
+        self.sync_zones().await?;
+        let mut current_ips = vec![];
+        for version in IpVersion::ALL {
+            match get_current_ip(version).await {
+                Ok(ip) => current_ips.push((version, ip)),
+                Err(e) => status!("Skipping {} records: {}", version, e),
+            }
+        }
+        if current_ips.is_empty() {
+            return Err(DnsCliError::IpDetection(
+                "Could not get the current IPv4 or IPv6".to_string(),
+            ));
+        }
+        let domains: Vec<String> = self.account.domains.keys().cloned().collect();
+        for domain in domains {
+            let mut imported = vec![];
+            for (version, ip) in current_ips.iter() {
+                let record_type = version.record_type();
+                let records = self
+                    .get_records(&domain, &record_type.to_string(), None)
+                    .await?;
+                for record in records.iter().filter(|r| r.data == *ip) {
+                    let name = full_name(&record.name, &domain);
+                    imported.push(Record {
+                        id: record_id(record_type, &name, &domain),
+                        name,
+                        record_type,
+                        content: None,
+                        settings: RecordSettings::default(),
+                    });
+                }
+            }
+            let zone = self.account.domains.get_mut(&domain).unwrap();
+            merge_imported(zone, imported, &current_ips)?;
+        }
+        CONFIG_SINGLETON
+            .lock()
+            .await
+            .save_account(self.account.clone())
+    }
+
+    async fn get_domain_details(&self, prefix: &str) -> Result<DomainDetails, DnsCliError> {
+        let record = self
+            .account
+            .domains
+            .values()
+            .flat_map(|zone| zone.records.iter())
+            .find(|record| {
+                record.name == prefix || record.name.starts_with(&format!("{}.", prefix))
+            })
+            .ok_or_else(|| {
+                DnsCliError::Config(format!("No domain found with prefix: {}", prefix))
+            })?;
+        let domain = find_zone_id(&self.account.domains, &record.name)
+            .ok_or_else(|| DnsCliError::Config(format!("No zone found for {}", record.name)))?;
+        let records = self.live_records(record, &domain).await?;
+        let found = records.first().ok_or_else(|| {
+            DnsCliError::Api(format!("No DNS record found with prefix: {}", prefix))
+        })?;
+        Ok(found.details(&domain))
+    }
+
+    async fn list_records(&self, zone_id: &str) -> Result<Vec<DomainDetails>, DnsCliError> {
+        let mut records = vec![];
+        loop {
+            let path = format!(
+                "/domains/{}/records?offset={}&limit={}",
+                zone_id,
+                records.len(),
+                PAGE_SIZE
+            );
+            let text = self.send_request::<()>(Method::GET, path, None).await?;
+            let page: Vec<GoDaddyRecord> = parse_response(&text)?;
+            let last_page = page.len() < PAGE_SIZE;
+            records.extend(page.iter().map(|record| record.details(zone_id)));
+            if last_page {
+                return Ok(records);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        full_name, record_entry, record_path, record_set, relative_name, GoDaddyRecord, SetChange,
+    };
+    use crate::config::{Record, RecordSettings, RecordType};
+    use crate::record_content::RecordContent;
+    use crate::test_fixtures::record;
+
+    #[test]
+    fn relative_name_round_trips() {
+        assert_eq!(relative_name("example.com", "example.com"), "@");
+        assert_eq!(relative_name("a.b.example.com", "example.com"), "a.b");
+        assert_eq!(full_name("@", "example.com"), "example.com");
+        assert_eq!(full_name("a.b", "example.com"), "a.b.example.com");
+    }
+
+    #[test]
+    fn srv_records_are_split_into_service_protocol_and_host() {
+        let record = Record {
+            content: Some(RecordContent {
+                value: "sip.example.com".to_string(),
+                priority: Some(10),
+                weight: Some(5),
+                port: Some(5060),
+                ..Default::default()
+            }),
+            ..record("_sip._tcp.office.example.com", RecordType::SRV)
+        };
+        assert_eq!(
+            record_path(&record, "example.com").unwrap(),
+            "/domains/example.com/records/SRV/office"
+        );
+        let entry = record_entry(&record, "example.com", "").unwrap();
+        assert_eq!(entry["service"], "_sip");
+        assert_eq!(entry["protocol"], "_tcp");
+        assert_eq!(entry["data"], "sip.example.com");
+        assert_eq!(entry["port"], 5060);
+    }
+
+    #[test]
+    fn srv_records_of_other_services_at_the_host_are_kept() {
+        let live: Vec<GoDaddyRecord> = serde_json::from_str(
+            r#"[
+                {"type": "SRV", "name": "office", "data": "sip.example.com", "ttl": 600,
+                 "service": "_sip", "protocol": "_tcp", "priority": 10, "weight": 5, "port": 5060},
+                {"type": "SRV", "name": "office", "data": "xmpp.example.com", "ttl": 3600,
+                 "service": "_xmpp", "protocol": "_tcp", "priority": 0, "weight": 0, "port": 5222}
+            ]"#,
+        )
+        .unwrap();
+        let record = Record {
+            content: Some(RecordContent {
+                value: "sip2.example.com".to_string(),
+                priority: Some(20),
+                weight: Some(5),
+                port: Some(5061),
+                ..Default::default()
+            }),
+            ..record("_sip._tcp.office.example.com", RecordType::SRV)
+        };
+        let entry = record_entry(&record, "example.com", "").unwrap();
+        let set = record_set(
+            &record,
+            "example.com",
+            "",
+            live.clone(),
+            SetChange::Update(entry),
+        )
+        .unwrap();
+        assert_eq!(set.len(), 2);
+        assert_eq!(set[0]["service"], "_xmpp");
+        assert_eq!(set[0]["ttl"], 3600);
+        assert_eq!(set[1]["data"], "sip2.example.com");
+        assert_eq!(set[1]["port"], 5061);
+
+        let set = record_set(&record, "example.com", "", live, SetChange::Remove).unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set[0]["data"], "xmpp.example.com");
+    }
+
+    #[test]
+    fn other_values_of_a_txt_set_are_kept() {
+        let live: Vec<GoDaddyRecord> = serde_json::from_str(
+            r#"[
+                {"type": "TXT", "name": "@", "data": "v=spf1 include:_spf.example.net -all", "ttl": 600},
+                {"type": "TXT", "name": "@", "data": "google-site-verification=abc", "ttl": 3600}
+            ]"#,
+        )
+        .unwrap();
+        let mut record = Record {
+            content: Some(RecordContent {
+                value: "v=spf1 include:_spf.example.net -all".to_string(),
+                ..Default::default()
+            }),
+            ..record("example.com", RecordType::TXT)
+        };
+        let set = record_set(&record, "example.com", "", live.clone(), SetChange::Remove).unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set[0]["data"], "google-site-verification=abc");
+        assert_eq!(set[0]["ttl"], 3600);
+
+        record.content.as_mut().unwrap().value = "hello".to_string();
+        let entry = record_entry(&record, "example.com", "").unwrap();
+        let set = record_set(
+            &record,
+            "example.com",
+            "",
+            live.clone(),
+            SetChange::Create(entry.clone()),
+        )
+        .unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set[2]["data"], "hello");
+
+        // An update that matches none of the values can't tell which one to replace
+        assert!(record_set(&record, "example.com", "", live, SetChange::Update(entry)).is_err());
+    }
+
+    #[test]
+    fn record_entry_keeps_the_ttl_and_rejects_cloudflare_settings() {
+        let mut record = Record {
+            settings: RecordSettings {
+                ttl: Some(3600),
+                ..Default::default()
+            },
+            ..record("ssh.example.com", RecordType::A)
+        };
+        let entry = record_entry(&record, "example.com", "192.0.2.1").unwrap();
+        assert_eq!(entry["ttl"], 3600);
+        assert_eq!(entry["data"], "192.0.2.1");
+
+        record.settings.proxied = Some(true);
+        assert!(record_entry(&record, "example.com", "192.0.2.1").is_err());
     }
 }
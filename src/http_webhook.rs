@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use crate::error::DnsCliError;
+use crate::output::status;
+use crate::secret::Secret;
+use crate::webhook_notifier::{default_events, EventKind, NotifyEvent, WebhookNotifier};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+
+/// A webhook for any service that takes an HTTP request, the body is a template where
+/// placeholders like `{{new_ip}}` are replaced with the values of the event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HttpWebhook {
+    /// Secret references are taken for the URL and the header values, which often hold tokens
+    url: Secret,
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    headers: BTreeMap<String, Secret>,
+    #[serde(default = "default_body")]
+    body: String,
+    #[serde(default = "default_events")]
+    events: Vec<EventKind>,
+}
+
+impl HttpWebhook {
+    pub fn new(
+        url: String,
+        method: String,
+        headers: BTreeMap<String, String>,
+        body: Option<String>,
+        events: Vec<EventKind>,
+    ) -> HttpWebhook {
+        HttpWebhook {
+            url: Secret::new(url),
+            method,
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| (name, Secret::new(value)))
+                .collect(),
+            body: body.unwrap_or_else(default_body),
+            events,
+        }
+    }
+
+    /// The URL as the config has it
+    pub fn url(&self) -> &str {
+        self.url.written()
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn events(&self) -> &[EventKind] {
+        &self.events
+    }
+
+    /// Whether the body is sent as JSON, going by the Content-Type header
+    fn sends_json(&self) -> bool {
+        self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("content-type")
+                && value.expose().to_lowercase().contains("json")
+        })
+    }
+
+    pub fn resolve_secrets(&mut self) -> Result<(), DnsCliError> {
+        let url = self.url.redacted_url();
+        self.url.resolve("the url of a webhook")?;
+        for (name, value) in &mut self.headers {
+            value.resolve(&format!("header {} of webhook {}", name, url))?;
+        }
+        Ok(())
+    }
+
+    /// The webhook with the token of its URL and the header values hidden, apart from the
+    /// headers that describe the body
+    pub fn redacted(&self) -> HttpWebhook {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match PLAIN_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+                    true => value.clone(),
+                    false => value.redacted(),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        HttpWebhook {
+            url: Secret::new(self.url.redacted_url()),
+            headers,
+            ..self.clone()
+        }
+    }
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+fn default_body() -> String {
+    "{{hostname}}: {{message}}".to_string()
+}
+
+/// Headers that never hold a secret and are shown as they are
+const PLAIN_HEADERS: [&str; 2] = ["Content-Type", "Accept"];
+
+const PLACEHOLDERS: [&str; 9] = [
+    "event",
+    "message",
+    "hostname",
+    "old_ip",
+    "new_ip",
+    "records_updated",
+    "failures",
+    "record",
+    "error",
+];
+
+/// Replaces the placeholders with their values, placeholders without a value become empty.
+/// With `json` set the values are escaped to fit in a JSON string.
+fn render(template: &str, values: &[(&str, String)], json: bool) -> String {
+    PLACEHOLDERS
+        .iter()
+        .fold(template.to_string(), |text, name| {
+            let value = values
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .unwrap_or_default();
+            let value = match json {
+                true => json_escape(value),
+                false => value.to_string(),
+            };
+            text.replace(&format!("{{{{{}}}}}", name), &value)
+        })
+}
+
+/// The text as it goes between the quotes of a JSON string
+fn json_escape(text: &str) -> String {
+    let quoted = serde_json::Value::String(text.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+impl WebhookNotifier for HttpWebhook {
+    async fn notify(&self, event: &NotifyEvent) {
+        let method = match Method::from_bytes(self.method.to_uppercase().as_bytes()) {
+            Ok(method) => method,
+            Err(_) => {
+                eprintln!(
+                    "Invalid method {} for webhook {}",
+                    self.method,
+                    self.url.redacted_url()
+                );
+                return;
+            }
+        };
+        let values = event.placeholders();
+        let mut request = Client::new()
+            .request(method, self.url.expose())
+            .body(render(&self.body, &values, self.sends_json()));
+        for (name, value) in &self.headers {
+            request = request.header(name, render(value.expose(), &values, false));
+        }
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => status!("Notified {}", self.url.redacted_url()),
+            Err(err) => eprintln!(
+                "Failed to notify {}: {}",
+                self.url.redacted_url(),
+                err.without_url()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, HttpWebhook};
+    use std::collections::BTreeMap;
+
+    fn values() -> Vec<(&'static str, String)> {
+        vec![
+            ("hostname", "vps1".to_string()),
+            ("old_ip", "198.51.100.1".to_string()),
+            ("new_ip", "203.0.113.7".to_string()),
+            (
+                "records_updated",
+                "a.example.com, b.example.com".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn render_replaces_every_placeholder() {
+        assert_eq!(
+            render(
+                r#"{"text": "{{hostname}}: {{old_ip}} -> {{new_ip}} ({{records_updated}})"}"#,
+                &values(),
+                false
+            ),
+            r#"{"text": "vps1: 198.51.100.1 -> 203.0.113.7 (a.example.com, b.example.com)"}"#
+        );
+    }
+
+    #[test]
+    fn render_empties_placeholders_without_value() {
+        assert_eq!(
+            render("[{{failures}}] {{error}} {{unknown}}", &values(), false),
+            "[]  {{unknown}}"
+        );
+    }
+
+    #[test]
+    fn render_escapes_values_for_json_bodies() {
+        let mut values = values();
+        values.push((
+            "error",
+            "Cloudflare returned: [{\"message\": \"bad\\token\"}]\nretrying".to_string(),
+        ));
+        let body = render(r#"{"text": "{{hostname}}: {{error}}"}"#, &values, true);
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            parsed["text"],
+            "vps1: Cloudflare returned: [{\"message\": \"bad\\token\"}]\nretrying"
+        );
+    }
+
+    #[test]
+    fn redacted_hides_tokens_but_not_the_content_type() {
+        let headers = BTreeMap::from([
+            ("Authorization".to_string(), "Bearer tk_123".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
+        let webhook = HttpWebhook::new(
+            "https://ntfy.example.com/alerts?auth=abc".to_string(),
+            "POST".to_string(),
+            headers,
+            None,
+            vec![],
+        );
+        let shown = serde_json::to_value(webhook.redacted()).unwrap();
+        assert_eq!(
+            shown["url"],
+            "https://ntfy.example.com/<redacted>?<redacted>"
+        );
+        assert_eq!(shown["headers"]["Authorization"], "<redacted>");
+        assert_eq!(shown["headers"]["Content-Type"], "application/json");
+        assert!(!format!("{:?}", webhook).contains("tk_123"));
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::OnceLock;
+
+use inquire::{Confirm, InquireError, Text};
+
+use crate::error::DnsCliError;
+
+/// Answers given on the command line for the questions dns-cli would otherwise ask, so it can
+/// run under cron or CI without a terminal
+#[derive(Debug, Clone, Default)]
+pub struct Answers {
+    /// Never ask, questions without an answer here fail or take the safe choice
+    pub non_interactive: bool,
+    /// Zones found at a provider that are added to the config
+    pub add_zones: Vec<String>,
+    /// Add every zone found at a provider
+    pub all_zones: bool,
+    /// Stop tracking the records that no longer point to this server
+    pub prune_missing: bool,
+}
+
+impl Answers {
+    /// The answer to adding a zone, None when it has to be asked
+    fn add_zone(&self, zone: &str) -> Option<bool> {
+        let zone = zone.trim_end_matches('.');
+        let named = self
+            .add_zones
+            .iter()
+            .any(|name| name.trim_end_matches('.').eq_ignore_ascii_case(zone));
+        if self.all_zones || named {
+            return Some(true);
+        }
+        self.non_interactive.then_some(false)
+    }
+
+    /// The answer to no longer tracking a record, None when it has to be asked
+    fn prune_missing(&self) -> Option<bool> {
+        if self.prune_missing {
+            return Some(true);
+        }
+        self.non_interactive.then_some(false)
+    }
+}
+
+static ANSWERS: OnceLock<Answers> = OnceLock::new();
+
+/// Sets the answers for the rest of the run, done once when the command line is read
+pub fn set_answers(answers: Answers) {
+    let _ = ANSWERS.set(answers);
+}
+
+fn answers() -> &'static Answers {
+    ANSWERS.get_or_init(Answers::default)
+}
+
+/// Whether a zone found at a provider is added to the config, by `--add-zone` or
+/// `--all-zones`, and without them by asking. Zones that are not named are left out when
+/// running non-interactively.
+pub fn add_zone(zone: &str) -> Result<bool, DnsCliError> {
+    if let Some(answer) = answers().add_zone(zone) {
+        return Ok(answer);
+    }
+    confirm(
+        &format!("Do you want to add the domain: {}", zone),
+        "pass --add-zone <ZONE> or --all-zones, and --yes to leave out the other zones",
+    )
+}
+
+/// Whether a tracked record that no longer points to this server stops being tracked, by
+/// `--prune-missing` and without it by asking. Records are kept when running
+/// non-interactively.
+pub fn prune_missing(name: &str) -> Result<bool, DnsCliError> {
+    if let Some(answer) = answers().prune_missing() {
+        return Ok(answer);
+    }
+    confirm(
+        &format!(
+            "{} no longer points to this server, stop tracking it?",
+            name
+        ),
+        "pass --prune-missing, or --yes to keep tracking it",
+    )
+}
+
+/// Asks to go ahead with a change, always yes when running non-interactively
+pub fn proceed(question: &str) -> Result<bool, DnsCliError> {
+    if answers().non_interactive {
+        return Ok(true);
+    }
+    confirm(question, "pass --yes")
+}
+
+/// Asks for a value that has no default, like a missing API token. Fails when running
+/// non-interactively, `hint` tells where to set the value instead.
+pub fn text(question: &str, hint: &str) -> Result<String, DnsCliError> {
+    if answers().non_interactive {
+        return Err(needs_input(question, hint));
+    }
+    Text::new(question).prompt().map_err(|err| match err {
+        InquireError::NotTTY => needs_input(question, hint),
+        err => DnsCliError::Config(format!("{}: {}", question, err)),
+    })
+}
+
+fn confirm(question: &str, hint: &str) -> Result<bool, DnsCliError> {
+    match Confirm::new(question).with_default(false).prompt() {
+        Ok(answer) => Ok(answer),
+        // Escape answers no, like the default
+        Err(InquireError::OperationCanceled) => Ok(false),
+        Err(InquireError::NotTTY) => Err(needs_input(question, hint)),
+        Err(err) => Err(DnsCliError::Config(format!("{}: {}", question, err))),
+    }
+}
+
+fn needs_input(question: &str, hint: &str) -> DnsCliError {
+    DnsCliError::Config(format!(
+        "Input is needed but none can be given: \"{}\", {}",
+        question, hint
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Answers;
+
+    #[test]
+    fn flags_answer_zone_questions_up_front() {
+        let interactive = Answers::default();
+        assert_eq!(interactive.add_zone("example.com"), None);
+
+        let named = Answers {
+            add_zones: vec!["Example.com.".to_string()],
+            non_interactive: true,
+            ..Default::default()
+        };
+        assert_eq!(named.add_zone("example.com"), Some(true));
+        assert_eq!(named.add_zone("example.org"), Some(false));
+
+        let all = Answers {
+            all_zones: true,
+            ..Default::default()
+        };
+        assert_eq!(all.add_zone("example.org"), Some(true));
+    }
+
+    #[test]
+    fn records_are_kept_unless_pruning() {
+        assert_eq!(Answers::default().prune_missing(), None);
+        let non_interactive = Answers {
+            non_interactive: true,
+            ..Default::default()
+        };
+        assert_eq!(non_interactive.prune_missing(), Some(false));
+        let prune = Answers {
+            prune_missing: true,
+            ..Default::default()
+        };
+        assert_eq!(prune.prune_missing(), Some(true));
+    }
+}
@@ -1,58 +1,211 @@
-use std::net::IpAddr;
-pub fn get_last_ip(debug: bool) -> Option<String> {
-    let home = home::home_dir().expect("Could not get home dir :(, please file bug report");
-
-    let path = home.join(".last_ip.txt");
-    if !path.exists() {
-        if debug {
-            println!("No last ip file found, probably first run");
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{RecordType, CONFIG_SINGLETON};
+use crate::error::DnsCliError;
+use crate::ip_source::IpSource;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    pub const ALL: [IpVersion; 2] = [IpVersion::V4, IpVersion::V6];
+
+    /// The address record type that points to an IP of this version
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            IpVersion::V4 => RecordType::A,
+            IpVersion::V6 => RecordType::AAAA,
+        }
+    }
+
+    /// The version of an address
+    pub fn of(ip: &IpAddr) -> IpVersion {
+        match ip {
+            IpAddr::V4(_) => IpVersion::V4,
+            IpAddr::V6(_) => IpVersion::V6,
+        }
+    }
+
+    /// The unspecified address of this version, binding to it forces traffic over this version
+    pub fn unspecified(&self) -> IpAddr {
+        match self {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+}
+
+/// How the current public IP is detected
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IpDetectionConfig {
+    #[serde(default)]
+    pub strategy: IpDetectionStrategy,
+    /// The sources to ask, in order
+    pub sources: Vec<IpSourceConfig>,
+}
+
+impl Default for IpDetectionConfig {
+    fn default() -> Self {
+        IpDetectionConfig {
+            strategy: IpDetectionStrategy::default(),
+            sources: vec![
+                IpSourceConfig {
+                    source: IpSource::Http {
+                        url: "https://api.ipify.org".to_string(),
+                    },
+                    version: Some(IpVersion::V4),
+                },
+                IpSourceConfig {
+                    source: IpSource::Http {
+                        url: "https://api6.ipify.org".to_string(),
+                    },
+                    version: Some(IpVersion::V6),
+                },
+            ],
         }
-        return None;
     }
-    parse_stored_ip(std::fs::read_to_string(path).unwrap())
 }
 
-fn is_valid_ip(ip: &str) -> bool {
-    ip.parse::<IpAddr>().is_ok()
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpDetectionStrategy {
+    /// Use the first source that answers
+    #[default]
+    Fallback,
+    /// Ask every source and use the IP that at least `required` of them agree on
+    Quorum { required: usize },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IpSourceConfig {
+    #[serde(flatten)]
+    pub source: IpSource,
+    /// Only use this source for one IP version, by default it is used for both
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<IpVersion>,
 }
 
-fn parse_stored_ip(ip: String) -> Option<String> {
-    let trimmed = ip.trim();
-    if trimmed.is_empty() {
-        return None;
+impl fmt::Display for IpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpVersion::V4 => write!(f, "IPv4"),
+            IpVersion::V6 => write!(f, "IPv6"),
+        }
     }
-    Some(trimmed.to_string())
 }
 
-pub async fn get_current_ip() -> Result<String, Box<dyn std::error::Error>> {
-    let ip = reqwest::get("https://api.ipify.org").await?.text().await?;
-    if !is_valid_ip(&ip) {
-        return Err("Could not get a valid IP".into());
+pub async fn get_current_ip(version: IpVersion) -> Result<String, DnsCliError> {
+    let detection = CONFIG_SINGLETON.lock().await.get().ip_detection;
+    detect_ip(&detection, version).await
+}
+
+async fn detect_ip(
+    detection: &IpDetectionConfig,
+    version: IpVersion,
+) -> Result<String, DnsCliError> {
+    let sources: Vec<&IpSource> = detection
+        .sources
+        .iter()
+        .filter(|config| config.version.is_none() || config.version == Some(version))
+        .map(|config| &config.source)
+        .collect();
+    if sources.is_empty() {
+        return Err(DnsCliError::IpDetection(format!(
+            "no source is configured for {}",
+            version
+        )));
+    }
+
+    let mut errors = vec![];
+    match detection.strategy {
+        IpDetectionStrategy::Fallback => {
+            for source in sources {
+                match source.lookup(version).await {
+                    Ok(ip) => return Ok(ip.to_string()),
+                    Err(err) => errors.push(source_error(err)),
+                }
+            }
+        }
+        IpDetectionStrategy::Quorum { required } => {
+            let mut answers = vec![];
+            for source in sources {
+                match source.lookup(version).await {
+                    Ok(ip) => answers.push(ip),
+                    Err(err) => errors.push(source_error(err)),
+                }
+            }
+            if let Some(ip) = quorum(&answers, required) {
+                return Ok(ip.to_string());
+            }
+            errors.push(format!(
+                "no {} of the answers {:?} agree",
+                required, answers
+            ));
+        }
     }
-    Ok(ip)
+    Err(DnsCliError::IpDetection(errors.join("; ")))
 }
 
-pub async fn save_ip(ip: &String) {
-    let home = home::home_dir().expect("Could not get home dir :(");
+fn source_error(err: DnsCliError) -> String {
+    match err {
+        DnsCliError::IpDetection(message) => message,
+        err => err.to_string(),
+    }
+}
 
-    let path = home.join(".last_ip.txt");
-    std::fs::write(path, ip).unwrap();
+/// The answer given by at least `required` sources
+fn quorum(answers: &[IpAddr], required: usize) -> Option<IpAddr> {
+    answers
+        .iter()
+        .find(|ip| answers.iter().filter(|other| other == ip).count() >= required.max(1))
+        .copied()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_stored_ip;
+    use super::{quorum, IpDetectionConfig, IpVersion};
+    use std::net::IpAddr;
+
+    #[test]
+    fn ip_version_of_checks_version() {
+        let v4: IpAddr = "203.0.113.7".parse().unwrap();
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(IpVersion::of(&v4), IpVersion::V4);
+        assert_eq!(IpVersion::of(&v6), IpVersion::V6);
+    }
 
     #[test]
-    fn parse_stored_ip_trims_whitespace() {
-        assert_eq!(
-            parse_stored_ip("  203.0.113.7\n".to_string()),
-            Some("203.0.113.7".to_string())
-        );
+    fn quorum_needs_enough_matching_answers() {
+        let a: IpAddr = "203.0.113.7".parse().unwrap();
+        let b: IpAddr = "203.0.113.8".parse().unwrap();
+        assert_eq!(quorum(&[a, b, a], 2), Some(a));
+        assert_eq!(quorum(&[a, b], 2), None);
+        assert_eq!(quorum(&[], 1), None);
     }
 
     #[test]
-    fn parse_stored_ip_empty_becomes_none() {
-        assert_eq!(parse_stored_ip("   \n\t".to_string()), None);
+    fn ip_detection_config_parses_sources() {
+        let config: IpDetectionConfig = serde_json::from_str(
+            r#"{
+                "strategy": {"type": "quorum", "required": 2},
+                "sources": [
+                    {"type": "http", "url": "https://api.ipify.org", "version": "v4"},
+                    {"type": "dns", "name": "myip.opendns.com", "server": "208.67.222.222"},
+                    {"type": "interface", "name": "eth0"},
+                    {"type": "command", "command": "get-ip"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.sources.len(), 4);
+        assert_eq!(config.sources[0].version, Some(IpVersion::V4));
+        assert_eq!(config.sources[3].version, None);
     }
 }
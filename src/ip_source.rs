@@ -0,0 +1,398 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use tokio::process::Command;
+
+use crate::error::DnsCliError;
+use crate::ip_handler::IpVersion;
+
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_AAAA: u16 = 28;
+
+/// A place the public IP can be read from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpSource {
+    /// A web service that answers with the IP of the caller as plain text
+    Http { url: String },
+    /// A DNS server that answers a query for `name` with the IP of the caller, like OpenDNS
+    /// answers `myip.opendns.com`. The server only sees the IP version it is queried over.
+    Dns { name: String, server: IpAddr },
+    /// The first address bound to a local network interface that is not filtered out
+    Interface {
+        name: String,
+        #[serde(flatten)]
+        filter: AddressFilter,
+    },
+    /// A program that prints the IP on stdout
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl IpSource {
+    pub async fn lookup(&self, version: IpVersion) -> Result<IpAddr, DnsCliError> {
+        let ip = match self {
+            IpSource::Http { url } => lookup_http(url, version).await?,
+            IpSource::Dns { name, server } => lookup_dns(name, *server, version).await?,
+            IpSource::Interface { name, filter } => lookup_interface(name, filter, version)?,
+            IpSource::Command { command, args } => lookup_command(command, args).await?,
+        };
+        if IpVersion::of(&ip) != version {
+            return Err(DnsCliError::IpDetection(format!(
+                "{} returned {}, which is not a valid {}",
+                self, ip, version
+            )));
+        }
+        Ok(ip)
+    }
+}
+
+impl std::fmt::Display for IpSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpSource::Http { url } => write!(f, "{}", url),
+            IpSource::Dns { name, server } => write!(f, "dns {} @{}", name, server),
+            IpSource::Interface { name, .. } => write!(f, "interface {}", name),
+            IpSource::Command { command, .. } => write!(f, "command {}", command),
+        }
+    }
+}
+
+fn parse_ip(source: &str, text: &str) -> Result<IpAddr, DnsCliError> {
+    let text = text.trim();
+    text.parse().map_err(|_| {
+        DnsCliError::IpDetection(format!(
+            "{} returned {:?}, which is not an IP",
+            source, text
+        ))
+    })
+}
+
+async fn lookup_http(url: &str, version: IpVersion) -> Result<IpAddr, DnsCliError> {
+    // Binding to the unspecified address of a version makes the request go out over that version
+    let client = reqwest::Client::builder()
+        .local_address(version.unspecified())
+        .timeout(LOOKUP_TIMEOUT)
+        .build()?;
+    let text = async {
+        client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+    .await
+    .map_err(|e| {
+        DnsCliError::IpDetection(format!("{} lookup at {} failed: {}", version, url, e))
+    })?;
+    parse_ip(url, &text)
+}
+
+async fn lookup_dns(name: &str, server: IpAddr, version: IpVersion) -> Result<IpAddr, DnsCliError> {
+    let error =
+        |e: String| DnsCliError::IpDetection(format!("dns lookup of {} @{}: {}", name, server, e));
+    let qtype = match version {
+        IpVersion::V4 => DNS_TYPE_A,
+        IpVersion::V6 => DNS_TYPE_AAAA,
+    };
+    let id = std::process::id() as u16;
+    let query = build_dns_query(id, name, qtype).map_err(error)?;
+    let socket = UdpSocket::bind(SocketAddr::new(IpVersion::of(&server).unspecified(), 0))
+        .await
+        .map_err(|e| error(e.to_string()))?;
+    socket
+        .send_to(&query, SocketAddr::new(server, 53))
+        .await
+        .map_err(|e| error(e.to_string()))?;
+    let mut buf = [0u8; 512];
+    let len = tokio::time::timeout(LOOKUP_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| error("timed out".to_string()))?
+        .map_err(|e| error(e.to_string()))?;
+    parse_dns_response(&buf[..len], id, qtype).map_err(error)
+}
+
+/// Which kinds of interface addresses are skipped, all of them are skipped by default since
+/// they can not be reached from the internet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AddressFilter {
+    /// fe80::/10 and 169.254.0.0/16
+    #[serde(default = "default_true")]
+    pub skip_link_local: bool,
+    /// Unique local IPv6 addresses, fc00::/7
+    #[serde(default = "default_true")]
+    pub skip_ula: bool,
+    /// 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16 and the carrier-grade NAT range 100.64.0.0/10
+    #[serde(default = "default_true")]
+    pub skip_private: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for AddressFilter {
+    fn default() -> Self {
+        AddressFilter {
+            skip_link_local: true,
+            skip_ula: true,
+            skip_private: true,
+        }
+    }
+}
+
+impl AddressFilter {
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        if ip.is_loopback() || ip.is_unspecified() {
+            return false;
+        }
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                let shared = a == 100 && (b & 0xc0) == 64;
+                !(self.skip_link_local && ip.is_link_local()
+                    || self.skip_private && (ip.is_private() || shared))
+            }
+            IpAddr::V6(ip) => {
+                let first = ip.segments()[0];
+                !(self.skip_link_local && (first & 0xffc0) == 0xfe80
+                    || self.skip_ula && (first & 0xfe00) == 0xfc00)
+            }
+        }
+    }
+}
+
+fn lookup_interface(
+    name: &str,
+    filter: &AddressFilter,
+    version: IpVersion,
+) -> Result<IpAddr, DnsCliError> {
+    let interfaces = if_addrs::get_if_addrs()?;
+    let addresses: Vec<IpAddr> = interfaces
+        .iter()
+        .filter(|interface| interface.name == name)
+        .map(|interface| interface.ip())
+        .filter(|ip| IpVersion::of(ip) == version)
+        .collect();
+    if addresses.is_empty() {
+        return Err(DnsCliError::IpDetection(format!(
+            "interface {} has no {} address",
+            name, version
+        )));
+    }
+    addresses
+        .into_iter()
+        .find(|ip| filter.allows(ip))
+        .ok_or_else(|| {
+            DnsCliError::IpDetection(format!(
+                "every {} address of interface {} is filtered out",
+                version, name
+            ))
+        })
+}
+
+async fn lookup_command(command: &str, args: &[String]) -> Result<IpAddr, DnsCliError> {
+    let output = tokio::time::timeout(LOOKUP_TIMEOUT, Command::new(command).args(args).output())
+        .await
+        .map_err(|_| DnsCliError::IpDetection(format!("command {} timed out", command)))??;
+    if !output.status.success() {
+        return Err(DnsCliError::IpDetection(format!(
+            "command {} exited with {}",
+            command, output.status
+        )));
+    }
+    parse_ip(command, &String::from_utf8_lossy(&output.stdout))
+}
+
+fn build_dns_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let mut query = vec![];
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid name {}", name));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    // Class IN
+    query.extend_from_slice(&1u16.to_be_bytes());
+    Ok(query)
+}
+
+/// Returns the offset right after the name starting at `offset`
+fn skip_dns_name(buf: &[u8], mut offset: usize) -> Result<usize, String> {
+    loop {
+        let len = *buf.get(offset).ok_or("truncated name")? as usize;
+        if len == 0 {
+            return Ok(offset + 1);
+        }
+        if len & 0xc0 == 0xc0 {
+            // Compression pointer, the name ends here
+            return Ok(offset + 2);
+        }
+        offset += len + 1;
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Result<u16, String> {
+    buf.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "truncated response".to_string())
+}
+
+fn parse_dns_response(buf: &[u8], id: u16, qtype: u16) -> Result<IpAddr, String> {
+    if read_u16(buf, 0)? != id {
+        return Err("response id does not match the query".to_string());
+    }
+    let rcode = read_u16(buf, 2)? & 0x000f;
+    if rcode != 0 {
+        return Err(format!("server answered with rcode {}", rcode));
+    }
+    let questions = read_u16(buf, 4)?;
+    let answers = read_u16(buf, 6)?;
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_dns_name(buf, offset)? + 4;
+    }
+    for _ in 0..answers {
+        offset = skip_dns_name(buf, offset)?;
+        let record_type = read_u16(buf, offset)?;
+        let data_len = read_u16(buf, offset + 8)? as usize;
+        let data = buf
+            .get(offset + 10..offset + 10 + data_len)
+            .ok_or("truncated answer")?;
+        offset += 10 + data_len;
+        match (record_type, data_len) {
+            (DNS_TYPE_A, 4) if qtype == DNS_TYPE_A => {
+                return Ok(IpAddr::V4(Ipv4Addr::new(
+                    data[0], data[1], data[2], data[3],
+                )));
+            }
+            (DNS_TYPE_AAAA, 16) if qtype == DNS_TYPE_AAAA => {
+                let octets: [u8; 16] = data.try_into().unwrap();
+                return Ok(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => continue,
+        }
+    }
+    Err("no address in the answer".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_dns_query, parse_dns_response, AddressFilter, IpSource, DNS_TYPE_A, DNS_TYPE_AAAA,
+    };
+    use std::net::IpAddr;
+
+    fn response(query: &[u8], record_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        // Mark as response with one answer
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 1;
+        // Name pointer to the question
+        response.extend_from_slice(&[0xc0, 0x0c]);
+        response.extend_from_slice(&record_type.to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, 0, 0, 0]);
+        response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        response.extend_from_slice(data);
+        response
+    }
+
+    #[test]
+    fn build_dns_query_encodes_labels() {
+        let query = build_dns_query(0x1234, "myip.opendns.com", DNS_TYPE_A).unwrap();
+        assert_eq!(&query[..2], &[0x12, 0x34]);
+        assert_eq!(&query[12..17], b"\x04myip");
+        assert_eq!(&query[query.len() - 4..], &[0, 1, 0, 1]);
+        assert!(build_dns_query(1, "bad..name", DNS_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn parse_dns_response_reads_a_and_aaaa() {
+        let query = build_dns_query(7, "myip.opendns.com", DNS_TYPE_A).unwrap();
+        let ip = parse_dns_response(
+            &response(&query, DNS_TYPE_A, &[203, 0, 113, 7]),
+            7,
+            DNS_TYPE_A,
+        );
+        assert_eq!(ip, Ok("203.0.113.7".parse::<IpAddr>().unwrap()));
+
+        let query = build_dns_query(8, "myip.opendns.com", DNS_TYPE_AAAA).unwrap();
+        let mut data = [0u8; 16];
+        data[0] = 0x20;
+        data[1] = 0x01;
+        data[2] = 0x0d;
+        data[3] = 0xb8;
+        data[15] = 1;
+        let ip = parse_dns_response(&response(&query, DNS_TYPE_AAAA, &data), 8, DNS_TYPE_AAAA);
+        assert_eq!(ip, Ok("2001:db8::1".parse::<IpAddr>().unwrap()));
+    }
+
+    #[test]
+    fn parse_dns_response_rejects_mismatched_id() {
+        let query = build_dns_query(7, "myip.opendns.com", DNS_TYPE_A).unwrap();
+        let answer = response(&query, DNS_TYPE_A, &[203, 0, 113, 7]);
+        assert!(parse_dns_response(&answer, 8, DNS_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn address_filter_skips_non_global_ranges_by_default() {
+        let filter = AddressFilter::default();
+        let allows = |ip: &str| filter.allows(&ip.parse().unwrap());
+        assert!(allows("203.0.113.7"));
+        assert!(allows("2001:db8::1"));
+        assert!(!allows("127.0.0.1"));
+        assert!(!allows("169.254.1.1"));
+        assert!(!allows("10.0.0.1"));
+        assert!(!allows("172.16.5.4"));
+        assert!(!allows("192.168.1.1"));
+        assert!(!allows("100.64.0.1"));
+        assert!(!allows("fe80::1"));
+        assert!(!allows("fd12:3456::1"));
+        assert!(!allows("::1"));
+    }
+
+    #[test]
+    fn address_filter_keeps_ranges_that_are_not_skipped() {
+        let filter = AddressFilter {
+            skip_link_local: true,
+            skip_ula: false,
+            skip_private: false,
+        };
+        assert!(filter.allows(&"192.168.1.1".parse().unwrap()));
+        assert!(filter.allows(&"fd12:3456::1".parse().unwrap()));
+        assert!(!filter.allows(&"fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn interface_source_parses_filter_flags() {
+        let source: IpSource =
+            serde_json::from_str(r#"{"type": "interface", "name": "eth0", "skip_private": false}"#)
+                .unwrap();
+        assert_eq!(
+            source,
+            IpSource::Interface {
+                name: "eth0".to_string(),
+                filter: AddressFilter {
+                    skip_link_local: true,
+                    skip_ula: true,
+                    skip_private: false,
+                },
+            }
+        );
+    }
+}
@@ -1,21 +1,50 @@
-use crate::config::Config;
+use crate::command_hook::{default_hook_events, CommandHook};
+use crate::config::{Config, ProviderAccount, ProviderKind, RecordSettings, RecordType};
+use crate::desired_state::DesiredState;
 use crate::discord_webhook::DiscordWebhook;
-use crate::webhook_notifier::WebhookNotifierType;
-use clap::{CommandFactory, Parser, Subcommand};
+use crate::error::DnsCliError;
+use crate::http_webhook::HttpWebhook;
+use crate::record_content::RecordContent;
+use crate::smtp_notifier::{SmtpNotifier, SmtpSecurity};
+use crate::webhook_notifier::{
+    default_events, numbered, EventKind, ListedNotifier, NotifyEvent, WebhookNotifier,
+    WebhookNotifierType,
+};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
-use cli_program::CLIProgram;
+use cli_program::{CLIProgram, RecordFilter};
 use config::CONFIG_SINGLETON;
-use dns_provider::DnsProvider;
+use daemon::WatchOptions;
+use dns_provider::{AnyProvider, DnsProvider};
+use interaction::Answers;
+use output::{status, OutputFormat};
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 mod cli_program;
 mod cloudflare_provider;
+mod command_hook;
 mod config;
+mod daemon;
+mod desired_state;
 mod discord_webhook;
 mod dns_provider;
+mod error;
+mod godaddy_api;
+mod http_webhook;
+mod interaction;
 mod ip_handler;
+mod ip_source;
+mod output;
+mod record_content;
+mod secret;
+mod smtp_notifier;
+mod state;
+#[cfg(test)]
+mod test_fixtures;
 mod webhook_notifier;
+mod zone_file;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, arg_required_else_help = true)]
@@ -24,6 +53,10 @@ pub struct Cli {
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// Sets a custom state file. Default file is next to the config file, e.g. ~/.config/dns-cli-config.state.json
+    #[arg(long, value_name = "FILE")]
+    state: Option<PathBuf>,
+
     /// Turn debugging information on
     #[arg(long, action = clap::ArgAction::Count)]
     debug: u8,
@@ -32,6 +65,27 @@ pub struct Cli {
     #[arg(short, long)]
     dry_run: bool,
 
+    /// Never asks for input. Questions are answered by --add-zone, --all-zones and --prune-missing, or
+    /// take the safe choice, and missing credentials are an error
+    #[arg(short = 'y', long, visible_alias = "yes", global = true)]
+    non_interactive: bool,
+
+    /// Adds this zone when a provider has zones that are not in the config, can be repeated
+    #[arg(long, value_name = "ZONE", global = true)]
+    add_zone: Vec<String>,
+
+    /// Adds every zone a provider has that is not in the config
+    #[arg(long, global = true)]
+    all_zones: bool,
+
+    /// Stops tracking the records that no longer point to this server when importing
+    #[arg(long, global = true)]
+    prune_missing: bool,
+
+    /// How results are printed, progress messages go to stderr for json and yaml
+    #[arg(short, long, value_enum, global = true, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -41,8 +95,69 @@ enum Commands {
     /// Prints the current config
     PrintConfig {},
 
+    /// Checks if IP has changed and if it has changed updates the tracked records in every account
+    Check {
+        /// Forces the update of the records even if the IP has not changed
+        #[arg(short, long)]
+        force: bool,
+
+        /// Compares the live content of every tracked record with the IP and updates the records that drifted
+        #[arg(short, long)]
+        reconcile: bool,
+    },
+
+    /// Keeps running and checks for a new IP on an interval, stops on SIGTERM and reloads the config on SIGHUP
+    #[command(visible_alias = "daemon")]
+    Watch {
+        /// Seconds between checks
+        #[arg(short, long, value_name = "SECONDS", default_value_t = 300)]
+        interval: u64,
+
+        /// Up to this many seconds are randomly added to every wait
+        #[arg(short, long, value_name = "SECONDS", default_value_t = 30)]
+        jitter: u64,
+
+        /// The longest wait in seconds after failed checks, the wait doubles with every failure in a row
+        #[arg(long, value_name = "SECONDS", default_value_t = 3600)]
+        max_backoff: u64,
+
+        /// Compares the live content of every tracked record with the IP on every check
+        #[arg(short, long)]
+        reconcile: bool,
+    },
+
+    /// Shows the changes that would make the zones match a desired-state file
+    Plan {
+        /// A YAML file, or a TOML file when it ends with .toml
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Also deletes the records of the zones that are not in the file
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Creates, updates and deletes records until the zones match a desired-state file
+    Apply {
+        /// A YAML file, or a TOML file when it ends with .toml
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Also deletes the records of the zones that are not in the file
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Commands for backing up and moving zones as BIND zone files
     #[command(subcommand)]
-    Cloudflare(DomainCommands),
+    Zone(ZoneCommands),
+
+    /// Commands for managing records in Cloudflare
+    Cloudflare(ProviderCommand),
+
+    /// Commands for managing records in GoDaddy
+    #[command(name = "godaddy")]
+    GoDaddy(ProviderCommand),
 
     /// Creates a new config file at the configured config path or default path.
     Init {},
@@ -51,6 +166,10 @@ enum Commands {
     #[command(subcommand)]
     Discord(WebhookCommands),
 
+    /// Commands for managing the notifiers that are told about IP changes
+    #[command(subcommand)]
+    Notify(NotifyCommands),
+
     /// Generate shell completion scripts
     Completion {
         /// Shell to generate completion for
@@ -59,6 +178,48 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand, PartialEq)]
+enum ZoneCommands {
+    /// Writes the records of a zone as a BIND zone file
+    Export {
+        /// The zone, like example.com
+        zone: String,
+
+        /// The account the zone is in, by default the first account that has it
+        #[arg(short, long)]
+        account: Option<String>,
+
+        /// Writes the zone file to this file instead of printing it
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+
+    /// Creates the records of a BIND zone file that the zone does not have yet
+    Import {
+        /// The zone file to read
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// The zone to import into, by default the first $ORIGIN of the file
+        #[arg(short, long)]
+        zone: Option<String>,
+
+        /// The account the zone is in, by default the first account that has it
+        #[arg(short, long)]
+        account: Option<String>,
+    },
+}
+
+#[derive(Args, PartialEq)]
+struct ProviderCommand {
+    /// The name of the account to use, by default all accounts of the provider are used
+    #[arg(short, long, global = true)]
+    account: Option<String>,
+
+    #[command(subcommand)]
+    command: DomainCommands,
+}
+
 #[derive(Subcommand, PartialEq)]
 enum DomainCommands {
     /// Checks if IP has changed and if it has changed updates all the DNS entries tied to this server
@@ -66,24 +227,77 @@ enum DomainCommands {
         /// Forces the update of the records even if the IP has not changed
         #[arg(short, long)]
         force: bool,
+
+        /// Compares the live content of every tracked record with the IP and updates the records that drifted
+        #[arg(short, long)]
+        reconcile: bool,
     },
     /// Creates a new subdomain and ties to to this server by updating the IP it points to to the current IP and adding it to the config file of domains to scrape
     Register {
-        /// The prefix will the domain will become this arg.example.org
+        /// The full name of the record, e.g. sub.example.org, the zone is picked by the longest matching zone name
         prefix: String,
+
+        /// The type of record to create, A and AAAA point the record to the IPv4 or IPv6 address of this server, other types need --content
+        #[arg(short = 't', long = "type", value_enum, default_value_t = RecordType::A)]
+        record_type: RecordType,
+
+        #[command(flatten)]
+        content: RecordContentArgs,
+
+        #[command(flatten)]
+        settings: RecordSettingsArgs,
+    },
+
+    /// Changes the content of a tracked CNAME, TXT, MX, SRV or CAA record, fields that are not given are kept
+    Update {
+        /// The full name of the record
+        prefix: String,
+
+        /// The type of the record, only needed when several tracked records have the name
+        #[arg(short = 't', long = "type", value_enum)]
+        record_type: Option<RecordType>,
+
+        #[command(flatten)]
+        content: RecordContentArgs,
     },
 
     /// Lists all the subdomains and their record types that are being tracked
-    Ls {},
+    Ls {
+        /// Lists every record of the configured zones as the provider has them, tracked records are marked with *
+        #[arg(short, long)]
+        remote: bool,
 
-    /// Deletes a subdomain (Not ready yet)
+        /// Only list records of this type, like A or MX
+        #[arg(short = 't', long = "type", value_name = "TYPE", requires = "remote")]
+        record_type: Option<String>,
+
+        /// Only list records whose name matches this glob, like '*.example.com'
+        #[arg(short, long, value_name = "GLOB", requires = "remote")]
+        name: Option<String>,
+
+        /// Only list records whose content contains this text
+        #[arg(long, requires = "remote")]
+        content: Option<String>,
+    },
+
+    /// Deletes a subdomain
     Rm {
-        /// The prefix of the subdomain that should be deleted
+        /// The full name of the record that should be deleted
         prefix: String,
+
+        /// The type of the record, only needed when several tracked records have the name
+        #[arg(short = 't', long = "type", value_enum)]
+        record_type: Option<RecordType>,
     },
-    /// Imports entries that have the same ip as the current ip
+    /// Imports A and AAAA entries that have the same ip as the current IPv4 or IPv6
     Import {},
 
+    /// Adds a new named account for the provider, asking for its credentials
+    AddAccount {
+        /// The name of the account
+        name: String,
+    },
+
     /// Fetches detailed information about a specific domain
     Inspect {
         /// The prefix of the subdomain to inspect
@@ -91,6 +305,84 @@ enum DomainCommands {
     },
 }
 
+/// The content of records that don't point to the IP of this server
+#[derive(Args, PartialEq, Default)]
+struct RecordContentArgs {
+    /// The target of CNAME, MX and SRV records, the text of TXT records or the value of CAA records
+    #[arg(long, visible_alias = "target")]
+    content: Option<String>,
+
+    /// The priority of MX and SRV records
+    #[arg(long)]
+    priority: Option<u16>,
+
+    /// The weight of SRV records
+    #[arg(long)]
+    weight: Option<u16>,
+
+    /// The port of SRV records
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// The flags of CAA records, 0 or 128 for critical
+    #[arg(long)]
+    flags: Option<u8>,
+
+    /// The tag of CAA records: issue, issuewild or iodef
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+impl RecordContentArgs {
+    fn is_empty(&self) -> bool {
+        *self == RecordContentArgs::default()
+    }
+
+    /// Overwrites the fields of `content` that were given on the command line
+    fn apply_to(self, mut content: RecordContent) -> RecordContent {
+        if let Some(value) = self.content {
+            content.value = value;
+        }
+        content.priority = self.priority.or(content.priority);
+        content.weight = self.weight.or(content.weight);
+        content.port = self.port.or(content.port);
+        content.flags = self.flags.or(content.flags);
+        content.tag = self.tag.or(content.tag);
+        content
+    }
+}
+
+/// Provider settings of a new record, they are kept in the config and sent on every update
+#[derive(Args, PartialEq)]
+struct RecordSettingsArgs {
+    /// Time to live in seconds, 1 is automatic at Cloudflare. By default the provider default
+    #[arg(long, value_name = "SECONDS")]
+    ttl: Option<u32>,
+
+    /// Whether Cloudflare proxies the traffic, pass false for DNS-only records. By default A and AAAA records are proxied
+    #[arg(long, value_name = "BOOL")]
+    proxied: Option<bool>,
+
+    /// A comment shown on the record in the Cloudflare dashboard
+    #[arg(long)]
+    comment: Option<String>,
+
+    /// Cloudflare tags like `team:infra`, comma separated
+    #[arg(long, value_delimiter = ',')]
+    tags: Vec<String>,
+}
+
+impl From<RecordSettingsArgs> for RecordSettings {
+    fn from(args: RecordSettingsArgs) -> RecordSettings {
+        RecordSettings {
+            ttl: args.ttl,
+            proxied: args.proxied,
+            comment: args.comment,
+            tags: args.tags,
+        }
+    }
+}
+
 #[derive(Subcommand, PartialEq)]
 enum WebhookCommands {
     /// Adds a new discord webhook to the config file
@@ -108,90 +400,604 @@ enum WebhookCommands {
     Ls {},
 }
 
+#[derive(Subcommand, PartialEq)]
+enum NotifyCommands {
+    /// Adds a new notifier to the config file
+    Add(AddNotifier),
+
+    /// Removes a notifier from the config file
+    Rm {
+        /// The number shown by `notify ls` or the url of the notifier
+        target: String,
+    },
+
+    /// Lists all the notifiers in the config file
+    Ls {},
+
+    /// Sends a sample event to the notifiers, whether they are subscribed to it or not
+    Test {
+        /// The number shown by `notify ls` or the url of the notifier, all notifiers when left out
+        target: Option<String>,
+
+        /// The kind of event to send
+        #[arg(short, long, value_enum, default_value_t = EventKind::IpChanged)]
+        event: EventKind,
+    },
+}
+
+#[derive(Args, PartialEq)]
+struct AddNotifier {
+    /// The events to notify about, comma separated. By default ip_changed, record_failed and ip_detection_failed
+    #[arg(short, long, global = true, value_enum, value_delimiter = ',')]
+    events: Vec<EventKind>,
+
+    #[command(subcommand)]
+    kind: NotifierKind,
+}
+
+#[derive(Subcommand, PartialEq)]
+enum NotifierKind {
+    /// A discord webhook
+    Discord {
+        /// The url of the discord webhook
+        url: String,
+    },
+
+    /// An HTTP request with a templated body
+    Http {
+        /// The url the request is sent to
+        url: String,
+
+        /// The HTTP method of the request
+        #[arg(short, long, default_value = "POST")]
+        method: String,
+
+        /// A header in the form `Name: value`, can be repeated
+        #[arg(short = 'H', long = "header", value_name = "HEADER")]
+        headers: Vec<String>,
+
+        /// The body template, {{old_ip}}, {{new_ip}}, {{hostname}}, {{records_updated}} and {{failures}} are replaced
+        #[arg(short, long)]
+        body: Option<String>,
+    },
+
+    /// Plain text emails sent over SMTP
+    Smtp {
+        /// The SMTP server
+        host: String,
+
+        /// The port of the SMTP server, by default 587 for starttls, 465 for tls and 25 for none
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// How the connection is secured
+        #[arg(short, long, value_enum, default_value_t = SmtpSecurity::StartTls)]
+        security: SmtpSecurity,
+
+        /// The user to log in as
+        #[arg(short, long)]
+        username: Option<String>,
+
+        /// The password to log in with
+        #[arg(long)]
+        password: Option<String>,
+
+        /// The sender address
+        #[arg(short, long)]
+        from: String,
+
+        /// A recipient address, can be repeated
+        #[arg(short, long, required = true)]
+        to: Vec<String>,
+    },
+
+    /// A local program that gets the event as DNS_CLI_* environment variables and as JSON on stdin, by default only run for ip_changed
+    Command {
+        /// The program to run
+        command: String,
+
+        /// Arguments passed to the program
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        /// Seconds the program may run before it is killed
+        #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+        timeout: u64,
+    },
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
-    let path = Config::get_config_path(cli.config.clone());
+    if let Err(err) = run(cli).await {
+        eprintln!("{}", err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), DnsCliError> {
     let dry_run = cli.dry_run;
+    output::set_format(cli.output);
+    interaction::set_answers(Answers {
+        non_interactive: cli.non_interactive,
+        add_zones: cli.add_zone.clone(),
+        all_zones: cli.all_zones,
+        prune_missing: cli.prune_missing,
+    });
 
     // Check if command is None, which should not happen due to arg_required_else_help = true
     // but we handle it gracefully anyway
     let command = match cli.command {
         Some(cmd) => cmd,
-        None => return, // clap will show help and exit before this point due to arg_required_else_help
+        None => return Ok(()), // clap will show help and exit before this point due to arg_required_else_help
     };
 
     // Handle completion command before initializing config
     if let Commands::Completion { shell } = command {
         generate_completion(shell);
-        return;
+        return Ok(());
     }
 
     if let Commands::Init {} = command {
         // If the command is Init, we don't need to load the config
         // because we are creating a new one
-        init(cli.config).await;
-        return;
+        return init(cli.config).await;
     }
-    CONFIG_SINGLETON.lock().await.init(path);
+    let path = Config::get_config_path(cli.config.clone())?;
+    CONFIG_SINGLETON.lock().await.init(path)?;
+    CONFIG_SINGLETON.lock().await.set_state_path(cli.state);
     let config = CONFIG_SINGLETON.lock().await.get();
 
     match command {
-        Commands::PrintConfig {} => {
-            println!("{:#?}", config);
+        Commands::PrintConfig {} => output::print(&config.redacted())?,
+        Commands::Init {} => init(cli.config).await?,
+        Commands::Check { force, reconcile } => {
+            check_all_accounts(config, cli.debug > 0, dry_run, force, reconcile).await?
+        }
+        Commands::Watch {
+            interval,
+            jitter,
+            max_backoff,
+            reconcile,
+        } => {
+            let options = WatchOptions {
+                interval: Duration::from_secs(interval.max(1)),
+                jitter: Duration::from_secs(jitter),
+                max_backoff: Duration::from_secs(max_backoff),
+                reconcile,
+            };
+            daemon::watch(options, cli.debug > 0, dry_run).await?
         }
-        Commands::Init {} => init(cli.config).await,
+        Commands::Plan { file, prune } => {
+            let state = DesiredState::load(&file)?;
+            let program =
+                zones_program(&state.zone_names(), config, cli.debug > 0, dry_run).await?;
+            output::print(&program.plan(&state, prune).await?)?
+        }
+        Commands::Apply { file, prune } => {
+            let state = DesiredState::load(&file)?;
+            let mut program =
+                zones_program(&state.zone_names(), config, cli.debug > 0, dry_run).await?;
+            let report = program.apply(&state, prune).await?;
+            // People saw the plan and the progress already
+            if output::is_machine() {
+                output::print(&report)?
+            }
+        }
+        Commands::Zone(cmd) => handle_zone_command(cmd, config, cli.debug > 0, dry_run).await?,
         Commands::Cloudflare(cmd) => {
-            let api = cloudflare_provider::CloudflareProvider::new().await;
-
-            let program = CLIProgram::new(api, cli.debug > 0, dry_run, config);
-            handle_domain_command(cmd, program).await;
+            handle_provider_command(
+                ProviderKind::Cloudflare,
+                cmd,
+                config,
+                cli.debug > 0,
+                dry_run,
+            )
+            .await?
+        }
+        Commands::GoDaddy(cmd) => {
+            handle_provider_command(ProviderKind::GoDaddy, cmd, config, cli.debug > 0, dry_run)
+                .await?
         }
         Commands::Completion { .. } => {
             // Already handled before config initialization
         }
         Commands::Discord(cmd) => match cmd {
             WebhookCommands::Add { url } => {
-                println!("Adding webhook: {}", url);
-                let discord_webhook = DiscordWebhook::new(url.to_string());
-                let config_path = Config::get_config_path(cli.config.clone());
-                let mut config = Config::get_config(&config_path).unwrap();
-                config.add_webhook(WebhookNotifierType::DiscordWebhook(discord_webhook));
-                config.write(&config_path);
+                status!("Adding webhook: {}", url);
+                add_notifier(WebhookNotifierType::DiscordWebhook(DiscordWebhook::new(
+                    url,
+                    default_events(),
+                )))
+                .await?
             }
             WebhookCommands::Rm { url } => {
-                println!("Removing webhook: {}", url);
+                status!("Removing webhook: {}", url);
+                remove_notifier(&url, |_, webhook| {
+                    matches!(webhook, WebhookNotifierType::DiscordWebhook(discord) if discord.url() == url)
+                })
+                .await?
             }
             WebhookCommands::Ls {} => {
-                println!("Listing webhooks");
+                let discord: Vec<ListedNotifier> = numbered(&config.webhooks)
+                    .into_iter()
+                    .filter(|listed| {
+                        matches!(listed.notifier(), WebhookNotifierType::DiscordWebhook(_))
+                    })
+                    .collect();
+                output::print(&discord)?
             }
         },
+        Commands::Notify(cmd) => handle_notify_command(cmd, config).await?,
     };
+    Ok(())
+}
+
+/// Checks for a new IP and updates the tracked records in every account
+async fn check_all_accounts(
+    config: Config,
+    debug: bool,
+    dry_run: bool,
+    force: bool,
+    reconcile: bool,
+) -> Result<(), DnsCliError> {
+    let mut apis = vec![];
+    for account in config.accounts.clone() {
+        apis.push(AnyProvider::new(account, debug).await?);
+    }
+
+    let program = CLIProgram::new(apis, debug, dry_run, config);
+    let report = program.check_for_new_ip(force, reconcile).await?;
+    output::print(&report)?;
+    report.result()
+}
+
+/// A program with the accounts that have one of the zones, given by name and optionally
+/// account, so no other account is asked for credentials
+async fn zones_program(
+    zones: &[(String, Option<String>)],
+    config: Config,
+    debug: bool,
+    dry_run: bool,
+) -> Result<CLIProgram<AnyProvider>, DnsCliError> {
+    let mut apis = vec![];
+    for account in config.accounts.clone() {
+        let used = zones.iter().any(|(zone, name)| {
+            name.as_ref().is_none_or(|name| *name == account.name)
+                && account.domains.values().any(|domain| {
+                    domain
+                        .domain
+                        .trim_end_matches('.')
+                        .eq_ignore_ascii_case(zone.trim_end_matches('.'))
+                })
+        });
+        if used {
+            apis.push(AnyProvider::new(account, debug).await?);
+        }
+    }
+    Ok(CLIProgram::new(apis, debug, dry_run, config))
+}
+
+async fn handle_zone_command(
+    cmd: ZoneCommands,
+    config: Config,
+    debug: bool,
+    dry_run: bool,
+) -> Result<(), DnsCliError> {
+    match cmd {
+        ZoneCommands::Export {
+            zone,
+            account,
+            file,
+        } => {
+            if file.is_none() && output::is_machine() {
+                return Err(DnsCliError::Config(
+                    "zone export prints a zone file, not JSON or YAML, pass --file to write it to a file"
+                        .to_string(),
+                ));
+            }
+            let zones = [(zone.clone(), account.clone())];
+            let program = zones_program(&zones, config, debug, dry_run).await?;
+            let text = program.export_zone(&zone, account.as_deref()).await?;
+            match file {
+                Some(file) => {
+                    std::fs::write(&file, text)?;
+                    status!("Exported {} to {}", zone, file.display());
+                }
+                None => print!("{}", text),
+            }
+            Ok(())
+        }
+        ZoneCommands::Import {
+            file,
+            zone,
+            account,
+        } => {
+            let text = std::fs::read_to_string(&file)?;
+            // The zone is only known after reading the file when --zone is not given
+            let zones: Vec<(String, Option<String>)> = match &zone {
+                Some(zone) => vec![(zone.clone(), account.clone())],
+                None => zone_file::parse(&text, None)?
+                    .origin
+                    .map(|origin| vec![(origin, account.clone())])
+                    .unwrap_or_default(),
+            };
+            let program = zones_program(&zones, config, debug, dry_run).await?;
+            let report = program
+                .import_zone(&text, zone.as_deref(), account.as_deref())
+                .await?;
+            // People saw the records to create and the progress already
+            if output::is_machine() {
+                output::print(&report)?
+            }
+            Ok(())
+        }
+    }
 }
 
-async fn handle_domain_command<T: DnsProvider>(cmd: DomainCommands, mut program: CLIProgram<T>) {
+async fn handle_notify_command(cmd: NotifyCommands, config: Config) -> Result<(), DnsCliError> {
     match cmd {
-        DomainCommands::Check { force } => program.check_for_new_ip(force.to_owned()).await,
-        DomainCommands::Ls {} => program.ls(),
-        DomainCommands::Register { prefix } => program.register_sub_domain(prefix).await,
-        DomainCommands::Rm { prefix } => program.remove_sub_domain(prefix).await,
+        NotifyCommands::Add(AddNotifier { events, kind }) => {
+            let defaults = match kind {
+                NotifierKind::Command { .. } => default_hook_events(),
+                _ => default_events(),
+            };
+            let events = if events.is_empty() { defaults } else { events };
+            let webhook = match kind {
+                NotifierKind::Discord { url } => {
+                    WebhookNotifierType::DiscordWebhook(DiscordWebhook::new(url, events))
+                }
+                NotifierKind::Http {
+                    url,
+                    method,
+                    headers,
+                    body,
+                } => {
+                    let headers = headers
+                        .iter()
+                        .map(|header| parse_header(header))
+                        .collect::<Result<_, _>>()?;
+                    WebhookNotifierType::HttpWebhook(HttpWebhook::new(
+                        url, method, headers, body, events,
+                    ))
+                }
+                NotifierKind::Smtp {
+                    host,
+                    port,
+                    security,
+                    username,
+                    password,
+                    from,
+                    to,
+                } => WebhookNotifierType::SmtpNotifier(SmtpNotifier::new(
+                    host, port, security, username, password, from, to, events,
+                )),
+                NotifierKind::Command {
+                    command,
+                    args,
+                    timeout,
+                } => WebhookNotifierType::CommandHook(CommandHook::new(
+                    command, args, timeout, events,
+                )),
+            };
+            status!("Adding notifier: {}", webhook);
+            add_notifier(webhook).await
+        }
+        NotifyCommands::Rm { target } => {
+            let index = notifier_index(&config, &target);
+            remove_notifier(&target, |position, webhook| match index {
+                Some(index) => position == index,
+                None => webhook.url() == target,
+            })
+            .await
+        }
+        NotifyCommands::Ls {} => output::print(&numbered(&config.webhooks)),
+        NotifyCommands::Test { target, event } => {
+            let webhooks: Vec<&WebhookNotifierType> = match &target {
+                Some(target) => {
+                    let index = notifier_index(&config, target);
+                    config
+                        .webhooks
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, webhook)| index == Some(*i) || webhook.url() == target)
+                        .map(|(_, webhook)| webhook)
+                        .collect()
+                }
+                None => config.webhooks.iter().collect(),
+            };
+            if webhooks.is_empty() {
+                return Err(DnsCliError::Config(
+                    "No matching notifier found".to_string(),
+                ));
+            }
+            let event = NotifyEvent::sample(event);
+            for webhook in webhooks {
+                status!("Sending a sample {} event to {}", event.kind(), webhook);
+                webhook.notify(&event).await;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The index of the notifier `target` names by its number in `notify ls`
+fn notifier_index(config: &Config, target: &str) -> Option<usize> {
+    target
+        .parse::<usize>()
+        .ok()
+        .filter(|number| *number >= 1 && *number <= config.webhooks.len())
+        .map(|number| number - 1)
+}
+
+fn parse_header(header: &str) -> Result<(String, String), DnsCliError> {
+    let (name, value) = header.split_once(':').ok_or_else(|| {
+        DnsCliError::Config(format!(
+            "Invalid header {:?}, expected `Name: value`",
+            header
+        ))
+    })?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+async fn add_notifier(webhook: WebhookNotifierType) -> Result<(), DnsCliError> {
+    let mut singleton = CONFIG_SINGLETON.lock().await;
+    let mut config = singleton.get();
+    config.add_webhook(webhook);
+    singleton.save(config)
+}
+
+async fn remove_notifier<F>(target: &str, matches: F) -> Result<(), DnsCliError>
+where
+    F: FnMut(usize, &WebhookNotifierType) -> bool,
+{
+    let mut singleton = CONFIG_SINGLETON.lock().await;
+    let mut config = singleton.get();
+    let removed = config.remove_webhook(target, matches)?;
+    singleton.save(config)?;
+    status!("Removed notifier: {}", removed);
+    Ok(())
+}
+
+async fn handle_provider_command(
+    kind: ProviderKind,
+    cmd: ProviderCommand,
+    config: Config,
+    debug: bool,
+    dry_run: bool,
+) -> Result<(), DnsCliError> {
+    if let DomainCommands::AddAccount { name } = cmd.command {
+        return add_account(kind, name, &config, debug).await;
+    }
+    let mut apis = vec![];
+    for account in provider_accounts(&config, kind, cmd.account.as_deref())? {
+        apis.push(AnyProvider::new(account, debug).await?);
+    }
+
+    let program = CLIProgram::new(apis, debug, dry_run, config);
+    handle_domain_command(cmd.command, program).await
+}
+
+async fn handle_domain_command<T: DnsProvider>(
+    cmd: DomainCommands,
+    mut program: CLIProgram<T>,
+) -> Result<(), DnsCliError> {
+    match cmd {
+        DomainCommands::Check { force, reconcile } => {
+            let report = program.check_for_new_ip(force, reconcile).await?;
+            output::print(&report)?;
+            report.result()
+        }
+        DomainCommands::Ls { remote: false, .. } => output::print(&program.ls()),
+        DomainCommands::Ls {
+            remote: true,
+            record_type,
+            name,
+            content,
+        } => {
+            let filter = RecordFilter {
+                record_type,
+                name,
+                content,
+            };
+            output::print(&program.ls_remote(&filter).await?)
+        }
+        DomainCommands::Register {
+            prefix,
+            record_type,
+            content,
+            settings,
+        } => {
+            let content = (!content.is_empty()).then(|| content.apply_to(RecordContent::default()));
+            program
+                .register_sub_domain(prefix, record_type, content, settings.into())
+                .await
+        }
+        DomainCommands::Update {
+            prefix,
+            record_type,
+            content,
+        } => {
+            if content.is_empty() {
+                return Err(DnsCliError::Config(
+                    "Nothing to update, pass the fields to change like --content".to_string(),
+                ));
+            }
+            program
+                .update_record_content(prefix, record_type, |current| content.apply_to(current))
+                .await
+        }
+        DomainCommands::Rm {
+            prefix,
+            record_type,
+        } => program.remove_sub_domain(prefix, record_type).await,
         DomainCommands::Import {} => program.import().await,
-        DomainCommands::Inspect { prefix } => program.inspect_domain(prefix).await,
+        DomainCommands::Inspect { prefix } => output::print(&program.inspect_domain(prefix).await?),
+        DomainCommands::AddAccount { .. } => {
+            // Handled before the providers are created
+            Ok(())
+        }
+    }
+}
+
+/// The accounts of a provider to run a command against. Either the named account or all accounts of
+/// the provider, a new account named after the provider is created when there is none
+fn provider_accounts(
+    config: &Config,
+    kind: ProviderKind,
+    name: Option<&str>,
+) -> Result<Vec<ProviderAccount>, DnsCliError> {
+    let accounts = config.accounts_of(kind);
+    if let Some(name) = name {
+        let account = accounts
+            .into_iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| {
+                DnsCliError::Config(format!(
+                    "No {} account named {}, add it with `dns-cli {} add-account {}`",
+                    kind, name, kind, name
+                ))
+            })?;
+        return Ok(vec![account]);
+    }
+    if accounts.is_empty() {
+        return Ok(vec![ProviderAccount::new(kind.to_string(), kind)]);
+    }
+    Ok(accounts)
+}
+
+async fn add_account(
+    kind: ProviderKind,
+    name: String,
+    config: &Config,
+    debug: bool,
+) -> Result<(), DnsCliError> {
+    if config.accounts.iter().any(|account| account.name == name) {
+        return Err(DnsCliError::Config(format!(
+            "An account named {} already exists",
+            name
+        )));
     }
+    status!("Adding {} account {}", kind, name);
+    // The provider asks for the credentials and saves the account
+    AnyProvider::new(ProviderAccount::new(name, kind), debug).await?;
+    status!("Done!");
+    Ok(())
 }
 
-async fn init(path: Option<PathBuf>) {
-    let path = Config::get_config_path(path);
+async fn init(path: Option<PathBuf>) -> Result<(), DnsCliError> {
+    let path = Config::get_config_path(path)?;
     if path.exists() {
-        panic!(
+        return Err(DnsCliError::Config(format!(
             "Config file already exists at {:?}, delete file to continue",
             path
-        );
+        )));
     }
 
-    println!("Creating new config file at path: {:?}", path);
-    Config::get_default_config().write(&path);
-    println!("Done!");
+    status!("Creating new config file at path: {:?}", path);
+    Config::get_default_config().write(&path)?;
+    status!("Done!");
+    Ok(())
 }
 
 /// Generate shell completion scripts for the specified shell
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::DnsCliError;
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for people
+    #[default]
+    Table,
+    Json,
+    Yaml,
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(OutputFormat::Table as u8);
+
+/// Sets the format for the rest of the run, done once when the command line is read
+pub fn set_format(format: OutputFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+pub fn format() -> OutputFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        value if value == OutputFormat::Json as u8 => OutputFormat::Json,
+        value if value == OutputFormat::Yaml as u8 => OutputFormat::Yaml,
+        _ => OutputFormat::Table,
+    }
+}
+
+/// Whether results are printed for scripts, progress messages then go to stderr so stdout
+/// only has the result
+pub fn is_machine() -> bool {
+    format() != OutputFormat::Table
+}
+
+/// Prints a progress message, to stdout for people and to stderr when the output is JSON or
+/// YAML
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::output::is_machine() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// The result of a command, printed as JSON, YAML or a table
+pub trait Output: Serialize {
+    fn print_table(&self);
+}
+
+/// Prints a result in the format given on the command line
+pub fn print<T: Output>(value: &T) -> Result<(), DnsCliError> {
+    let invalid = |e: String| DnsCliError::Config(format!("Failed to write the output: {}", e));
+    match format() {
+        OutputFormat::Table => value.print_table(),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(value).map_err(|e| invalid(e.to_string()))?
+        ),
+        OutputFormat::Yaml => print!(
+            "{}",
+            serde_yaml::to_string(value).map_err(|e| invalid(e.to_string()))?
+        ),
+    }
+    Ok(())
+}
+
+/// Prints rows in columns padded to their widest cell, the last column is not padded
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let last = row.len() - 1;
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| match column {
+                column if column == last => cell.clone(),
+                column => format!("{:<width$}", cell, width = widths[column]),
+            })
+            .collect();
+        println!("{}", cells.join("  "));
+    }
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::RecordType;
+use crate::error::DnsCliError;
+
+/// The content of a record that does not point to the IP of this server, like the target of a
+/// CNAME or the text of a TXT record. Which fields are used depends on the record type.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordContent {
+    /// The target of CNAME, MX and SRV records, the text of TXT records or the value of CAA records
+    pub value: String,
+    /// Used by MX and SRV records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u16>,
+    /// Used by SRV records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u16>,
+    /// Used by SRV records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Used by CAA records, 0 when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flags: Option<u8>,
+    /// Used by CAA records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+const CAA_TAGS: [&str; 3] = ["issue", "issuewild", "iodef"];
+
+impl RecordContent {
+    /// The content in zone file notation, e.g. `10 mail.example.com` for an MX record
+    pub fn rdata(&self, record_type: RecordType) -> String {
+        match record_type {
+            RecordType::MX => format!("{} {}", self.priority.unwrap_or_default(), self.value),
+            RecordType::SRV => format!(
+                "{} {} {} {}",
+                self.priority.unwrap_or_default(),
+                self.weight.unwrap_or_default(),
+                self.port.unwrap_or_default(),
+                self.value
+            ),
+            RecordType::CAA => format!(
+                "{} {} {}",
+                self.flags.unwrap_or_default(),
+                self.tag.as_deref().unwrap_or_default(),
+                quote(&self.value)
+            ),
+            RecordType::TXT => quote(&self.value),
+            _ => self.value.clone(),
+        }
+    }
+
+    /// The fields that are set, by the name of their command line flag
+    fn set_fields(&self) -> [(&'static str, bool); 5] {
+        [
+            ("priority", self.priority.is_some()),
+            ("weight", self.weight.is_some()),
+            ("port", self.port.is_some()),
+            ("flags", self.flags.is_some()),
+            ("tag", self.tag.is_some()),
+        ]
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Checks a record before it is sent to a provider. A and AAAA records point to the IP of this
+/// server and take no content, every other type needs content with the fields of its type.
+pub fn validate_record(
+    record_type: RecordType,
+    name: &str,
+    content: Option<&RecordContent>,
+) -> Result<(), DnsCliError> {
+    let invalid = |reason: String| {
+        DnsCliError::Config(format!(
+            "Invalid {} record {}: {}",
+            record_type, name, reason
+        ))
+    };
+    let content = match (record_type.ip_version(), content) {
+        (Some(_), None) => return Ok(()),
+        (Some(version), Some(_)) => {
+            return Err(invalid(format!(
+                "it points to the {} of this server and takes no content",
+                version
+            )))
+        }
+        (None, None) => return Err(invalid("no content given".to_string())),
+        (None, Some(content)) => content,
+    };
+    let allowed: &[&str] = match record_type {
+        RecordType::MX => &["priority"],
+        RecordType::SRV => &["priority", "weight", "port"],
+        RecordType::CAA => &["flags", "tag"],
+        _ => &[],
+    };
+    for (field, set) in content.set_fields() {
+        if set && !allowed.contains(&field) {
+            return Err(invalid(format!(
+                "{} is not used by {} records",
+                field, record_type
+            )));
+        }
+        if !set && allowed.contains(&field) && field != "flags" {
+            return Err(invalid(format!("{} is required", field)));
+        }
+    }
+    if content.value.is_empty() {
+        return Err(invalid("no content given".to_string()));
+    }
+    match record_type {
+        RecordType::CNAME => check_hostname(&content.value).map_err(invalid),
+        // A target of `.` means the domain offers no mail or no such service
+        RecordType::MX | RecordType::SRV if content.value == "." => Ok(()),
+        RecordType::MX => check_hostname(&content.value).map_err(invalid),
+        RecordType::SRV => {
+            check_srv_name(name).map_err(invalid)?;
+            check_hostname(&content.value).map_err(invalid)
+        }
+        RecordType::CAA => {
+            let tag = content.tag.as_deref().unwrap_or_default();
+            if !CAA_TAGS.contains(&tag) {
+                return Err(invalid(format!(
+                    "tag must be one of {}, got {:?}",
+                    CAA_TAGS.join(", "),
+                    tag
+                )));
+            }
+            if !matches!(content.flags, None | Some(0) | Some(128)) {
+                return Err(invalid("flags must be 0 or 128 (critical)".to_string()));
+            }
+            let url_schemes = ["mailto:", "http://", "https://"];
+            if tag == "iodef" && !url_schemes.iter().any(|s| content.value.starts_with(s)) {
+                return Err(invalid(
+                    "an iodef value must be a mailto:, http:// or https:// URL".to_string(),
+                ));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A host name of labels with letters, digits, hyphens and underscores, a trailing dot is allowed
+fn check_hostname(host: &str) -> Result<(), String> {
+    let trimmed = host.strip_suffix('.').unwrap_or(host);
+    if trimmed.is_empty() || trimmed.len() > 253 {
+        return Err(format!("{:?} is not a valid host name", host));
+    }
+    for label in trimmed.split('.') {
+        let valid = !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("{:?} is not a valid host name", host));
+        }
+    }
+    Ok(())
+}
+
+/// SRV records are named `_service._protocol.name`
+fn check_srv_name(name: &str) -> Result<(), String> {
+    let mut labels = name.split('.');
+    let service = labels.next().unwrap_or_default();
+    let protocol = labels.next().unwrap_or_default();
+    if service.len() < 2
+        || !service.starts_with('_')
+        || protocol.len() < 2
+        || !protocol.starts_with('_')
+    {
+        return Err(format!(
+            "the name must start with _service._protocol, like _sip._tcp.{}",
+            name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_record, RecordContent};
+    use crate::config::RecordType;
+
+    fn content(value: &str) -> RecordContent {
+        RecordContent {
+            value: value.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn address_records_take_no_content() {
+        assert!(validate_record(RecordType::A, "a.example.com", None).is_ok());
+        assert!(validate_record(RecordType::AAAA, "a.example.com", Some(&content("::1"))).is_err());
+        assert!(validate_record(RecordType::TXT, "a.example.com", None).is_err());
+    }
+
+    #[test]
+    fn type_specific_fields_are_required_and_checked() {
+        let mx = RecordContent {
+            priority: Some(10),
+            ..content("mail.example.com")
+        };
+        assert!(validate_record(RecordType::MX, "example.com", Some(&mx)).is_ok());
+        assert!(validate_record(
+            RecordType::MX,
+            "example.com",
+            Some(&content("mail.example.com"))
+        )
+        .is_err());
+        assert!(validate_record(RecordType::CNAME, "www.example.com", Some(&mx)).is_err());
+        assert!(validate_record(
+            RecordType::CNAME,
+            "www.example.com",
+            Some(&content("bad host"))
+        )
+        .is_err());
+
+        let srv = RecordContent {
+            priority: Some(10),
+            weight: Some(5),
+            port: Some(5060),
+            ..content("sip.example.com")
+        };
+        assert!(validate_record(RecordType::SRV, "_sip._tcp.example.com", Some(&srv)).is_ok());
+        assert!(validate_record(RecordType::SRV, "sip.example.com", Some(&srv)).is_err());
+
+        let caa = RecordContent {
+            tag: Some("issue".to_string()),
+            ..content("letsencrypt.org")
+        };
+        assert!(validate_record(RecordType::CAA, "example.com", Some(&caa)).is_ok());
+        let bad_flags = RecordContent {
+            flags: Some(1),
+            ..caa.clone()
+        };
+        assert!(validate_record(RecordType::CAA, "example.com", Some(&bad_flags)).is_err());
+        let bad_tag = RecordContent {
+            tag: Some("issues".to_string()),
+            ..caa
+        };
+        assert!(validate_record(RecordType::CAA, "example.com", Some(&bad_tag)).is_err());
+    }
+
+    #[test]
+    fn rdata_uses_zone_file_notation() {
+        let srv = RecordContent {
+            priority: Some(10),
+            weight: Some(5),
+            port: Some(5060),
+            ..content("sip.example.com")
+        };
+        assert_eq!(srv.rdata(RecordType::SRV), "10 5 5060 sip.example.com");
+        let caa = RecordContent {
+            tag: Some("issue".to_string()),
+            ..content("letsencrypt.org")
+        };
+        assert_eq!(caa.rdata(RecordType::CAA), "0 issue \"letsencrypt.org\"");
+        assert_eq!(
+            content("v=spf1 \"x\"").rdata(RecordType::TXT),
+            "\"v=spf1 \\\"x\\\"\""
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::DnsCliError;
+
+/// The keyring service used when a `keyring:` reference names only the entry
+const KEYRING_SERVICE: &str = "dns-cli";
+
+/// Shown instead of a secret that is written in the config itself
+const REDACTED: &str = "<redacted>";
+
+/// A credential from the config. It is either written in the config or a reference to where
+/// it is kept: `env:NAME` for an environment variable, `file:PATH` for a file, or
+/// `keyring:SERVICE/ENTRY` (or `keyring:ENTRY` in the dns-cli service) for the OS keyring.
+/// The config keeps what was written, references are never replaced by their value.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret {
+    /// What the config has
+    written: String,
+    /// The value of a reference once resolved
+    value: Option<String>,
+}
+
+impl Secret {
+    /// A secret written in the config, like a token that was asked for
+    pub fn new(value: String) -> Secret {
+        Secret {
+            written: value,
+            value: None,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        ["env:", "file:", "keyring:"]
+            .iter()
+            .any(|prefix| self.written.starts_with(prefix))
+    }
+
+    /// Looks up the value of a reference, done when the config is loaded. `what` names the
+    /// setting for the error message.
+    pub fn resolve(&mut self, what: &str) -> Result<(), DnsCliError> {
+        let failed = |reason: String| {
+            DnsCliError::Config(format!(
+                "Could not read {} from {}: {}",
+                what, self.written, reason
+            ))
+        };
+        let value = if let Some(name) = self.written.strip_prefix("env:") {
+            std::env::var(name).map_err(|_| failed("the variable is not set".to_string()))?
+        } else if let Some(path) = self.written.strip_prefix("file:") {
+            std::fs::read_to_string(path)
+                .map_err(|e| failed(e.to_string()))?
+                .trim_end_matches(['\r', '\n'])
+                .to_string()
+        } else if let Some(entry) = self.written.strip_prefix("keyring:") {
+            let (service, user) = entry.split_once('/').unwrap_or((KEYRING_SERVICE, entry));
+            // The Linux keyring blocks on a runtime of its own, which can't be started on a
+            // thread that already runs the one of dns-cli
+            std::thread::scope(|scope| {
+                scope
+                    .spawn(|| keyring::Entry::new(service, user)?.get_password())
+                    .join()
+            })
+            .map_err(|_| failed("the keyring lookup crashed".to_string()))?
+            .map_err(|e| failed(e.to_string()))?
+        } else {
+            return Ok(());
+        };
+        if value.is_empty() {
+            return Err(failed("it is empty".to_string()));
+        }
+        self.value = Some(value);
+        Ok(())
+    }
+
+    /// The secret to send to the provider
+    pub fn expose(&self) -> &str {
+        self.value.as_deref().unwrap_or(&self.written)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expose().is_empty()
+    }
+
+    /// The secret for showing the config, references are kept and values are hidden
+    pub fn redacted(&self) -> Secret {
+        if self.is_reference() || self.written.is_empty() {
+            return Secret::new(self.written.clone());
+        }
+        Secret::new(REDACTED.to_string())
+    }
+
+    /// What the config has, the reference or the secret itself
+    pub fn written(&self) -> &str {
+        &self.written
+    }
+
+    /// A URL for showing, like `redacted` but only the last part of the path and the query
+    /// are hidden, which is where webhook URLs keep their token
+    pub fn redacted_url(&self) -> String {
+        if self.is_reference() || self.written.is_empty() {
+            return self.written.clone();
+        }
+        let (url, query) = match self.written.split_once('?') {
+            Some((url, _)) => (url, format!("?{}", REDACTED)),
+            None => (self.written.as_str(), String::new()),
+        };
+        let path_start = url
+            .find("://")
+            .and_then(|scheme| url[scheme + 3..].find('/').map(|host| scheme + 3 + host));
+        match (path_start, url.rsplit_once('/')) {
+            (Some(start), Some((base, last))) if base.len() >= start && !last.is_empty() => {
+                format!("{}/{}{}", base, REDACTED, query)
+            }
+            _ => format!("{}{}", url, query),
+        }
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.redacted().written)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.written)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Secret;
+
+    #[test]
+    fn references_resolve_but_are_saved_as_written() {
+        std::env::set_var("DNS_CLI_TEST_TOKEN", "from-env");
+        let mut secret = Secret::new("env:DNS_CLI_TEST_TOKEN".to_string());
+        secret.resolve("api_token").unwrap();
+        assert_eq!(secret.expose(), "from-env");
+        assert_eq!(
+            serde_json::to_string(&secret).unwrap(),
+            "\"env:DNS_CLI_TEST_TOKEN\""
+        );
+        assert_eq!(format!("{:?}", secret), "\"env:DNS_CLI_TEST_TOKEN\"");
+
+        let path = std::env::temp_dir().join("dns-cli-secret-test");
+        std::fs::write(&path, "from-file\n").unwrap();
+        let mut secret = Secret::new(format!("file:{}", path.display()));
+        secret.resolve("api_token").unwrap();
+        assert_eq!(secret.expose(), "from-file");
+        std::fs::remove_file(path).unwrap();
+
+        let mut missing = Secret::new("env:DNS_CLI_TEST_UNSET".to_string());
+        assert!(missing.resolve("api_token").is_err());
+    }
+
+    #[test]
+    fn written_secrets_are_redacted() {
+        let mut secret = Secret::new("plain-token".to_string());
+        secret.resolve("api_token").unwrap();
+        assert_eq!(secret.expose(), "plain-token");
+        assert_eq!(format!("{:?}", secret), "\"<redacted>\"");
+        assert_eq!(secret.redacted().expose(), "<redacted>");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"plain-token\"");
+        assert!(Secret::default().redacted().is_empty());
+    }
+
+    #[test]
+    fn urls_keep_all_but_their_token() {
+        let discord = Secret::new("https://discord.com/api/webhooks/123/abc-token".to_string());
+        assert_eq!(
+            discord.redacted_url(),
+            "https://discord.com/api/webhooks/123/<redacted>"
+        );
+        let query = Secret::new("https://example.com/hook?token=abc".to_string());
+        assert_eq!(
+            query.redacted_url(),
+            "https://example.com/<redacted>?<redacted>"
+        );
+        let bare = Secret::new("https://example.com".to_string());
+        assert_eq!(bare.redacted_url(), "https://example.com");
+        let reference = Secret::new("env:DISCORD_URL".to_string());
+        assert_eq!(reference.redacted_url(), "env:DISCORD_URL");
+    }
+}
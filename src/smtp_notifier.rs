@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use crate::error::DnsCliError;
+use crate::output::status;
+use crate::secret::Secret;
+use crate::webhook_notifier::{
+    default_events, local_hostname, EventKind, NotifyEvent, WebhookNotifier,
+};
+use clap::ValueEnum;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the connection to the SMTP server is secured
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS, port 587 by default
+    #[default]
+    #[value(name = "starttls")]
+    StartTls,
+    /// TLS from the start, port 465 by default
+    Tls,
+    /// No encryption at all, port 25 by default. Only for servers on a trusted network
+    None,
+}
+
+/// Sends events as plain text emails
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmtpNotifier {
+    host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(default)]
+    security: SmtpSecurity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password: Option<Secret>,
+    from: String,
+    to: Vec<String>,
+    #[serde(default = "default_events")]
+    events: Vec<EventKind>,
+}
+
+impl SmtpNotifier {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        host: String,
+        port: Option<u16>,
+        security: SmtpSecurity,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+        events: Vec<EventKind>,
+    ) -> SmtpNotifier {
+        SmtpNotifier {
+            host,
+            port,
+            security,
+            username,
+            password: password.map(Secret::new),
+            from,
+            to,
+            events,
+        }
+    }
+
+    /// Reads the password when it is kept in an environment variable, a file or the keyring
+    pub fn resolve_secrets(&mut self) -> Result<(), DnsCliError> {
+        match &mut self.password {
+            Some(password) => password.resolve(&format!("the password for {}", self.host)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn redacted(&self) -> SmtpNotifier {
+        SmtpNotifier {
+            password: self.password.as_ref().map(Secret::redacted),
+            ..self.clone()
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn recipients(&self) -> &[String] {
+        &self.to
+    }
+
+    pub fn events(&self) -> &[EventKind] {
+        &self.events
+    }
+
+    fn message(&self, event: &NotifyEvent) -> Result<Message, String> {
+        let hostname = local_hostname();
+        let mut builder = Message::builder()
+            .from(
+                self.from
+                    .parse()
+                    .map_err(|e| format!("from {}: {}", self.from, e))?,
+            )
+            .subject(format!("[dns-cli] {} on {}", event.kind(), hostname))
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.parse().map_err(|e| format!("to {}: {}", to, e))?);
+        }
+        let mut body = format!("{}\n\n", event.message());
+        for (name, value) in event.placeholders() {
+            if name != "message" && !value.is_empty() {
+                body.push_str(&format!("{}: {}\n", name, value));
+            }
+        }
+        builder.body(body).map_err(|e| e.to_string())
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+        let mut builder = match self.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)
+                    .map_err(|e| e.to_string())?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)
+                .map_err(|e| e.to_string())?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host)
+            }
+        };
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose().to_string(),
+            ));
+        }
+        Ok(builder.timeout(Some(SMTP_TIMEOUT)).build())
+    }
+
+    async fn send(&self, event: &NotifyEvent) -> Result<(), String> {
+        let message = self.message(event)?;
+        self.transport()?
+            .send(message)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+impl WebhookNotifier for SmtpNotifier {
+    async fn notify(&self, event: &NotifyEvent) {
+        match self.send(event).await {
+            Ok(()) => status!("Emailed {}", self.to.join(", ")),
+            Err(err) => eprintln!("Failed to email {}: {}", self.to.join(", "), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmtpNotifier, SmtpSecurity};
+    use crate::webhook_notifier::{default_events, EventKind, NotifyEvent};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Accepts one SMTP session and returns the commands and the message it received
+    async fn mock_smtp_server(listener: TcpListener) -> (Vec<String>, String) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 mock ESMTP\r\n").await.unwrap();
+        let mut commands = vec![];
+        let mut data = String::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let command = line.to_uppercase();
+            commands.push(line.clone());
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250 mock\r\n"
+            } else if command == "DATA" {
+                write.write_all(b"354 go ahead\r\n").await.unwrap();
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    if line == "." {
+                        break;
+                    }
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                b"250 queued\r\n"
+            } else if command == "QUIT" {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        (commands, data)
+    }
+
+    #[tokio::test]
+    async fn sends_event_as_plain_text_email() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(mock_smtp_server(listener));
+
+        let notifier = SmtpNotifier::new(
+            "127.0.0.1".to_string(),
+            Some(port),
+            SmtpSecurity::None,
+            None,
+            None,
+            "dns-cli@example.com".to_string(),
+            vec!["oncall@example.com".to_string()],
+            default_events(),
+        );
+        notifier
+            .send(&NotifyEvent::sample(EventKind::RecordFailed))
+            .await
+            .unwrap();
+
+        let (commands, data) = server.await.unwrap();
+        assert!(commands
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case("MAIL FROM:<dns-cli@example.com>")));
+        assert!(commands
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case("RCPT TO:<oncall@example.com>")));
+        assert!(data.contains("Subject: [dns-cli] record_failed on"));
+        assert!(data.contains("Content-Type: text/plain"));
+        assert!(
+            data.contains("Failed to update A record test.example.com: API error: sample failure")
+        );
+    }
+
+    #[test]
+    fn invalid_recipient_is_rejected() {
+        let notifier = SmtpNotifier::new(
+            "localhost".to_string(),
+            None,
+            SmtpSecurity::StartTls,
+            None,
+            None,
+            "dns-cli@example.com".to_string(),
+            vec!["not an address".to_string()],
+            default_events(),
+        );
+        assert!(notifier
+            .message(&NotifyEvent::sample(EventKind::IpChanged))
+            .is_err());
+    }
+}
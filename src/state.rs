@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::DnsCliError;
+use crate::ip_handler::IpVersion;
+
+/// What `check` remembers between runs, stored as JSON next to the config file
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct State {
+    #[serde(default)]
+    pub ipv4: IpState,
+    #[serde(default)]
+    pub ipv6: IpState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_check: Option<DateTime<Utc>>,
+    /// Keyed by `account/record id`
+    #[serde(default)]
+    pub records: BTreeMap<String, RecordState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct IpState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_change: Option<DateTime<Utc>>,
+    /// Keys of the records that failed to update to `ip` and are retried on the next check
+    #[serde(default)]
+    pub pending_failures: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordState {
+    pub last_success: DateTime<Utc>,
+    pub ip: String,
+}
+
+impl State {
+    pub fn ip(&self, version: IpVersion) -> &IpState {
+        match version {
+            IpVersion::V4 => &self.ipv4,
+            IpVersion::V6 => &self.ipv6,
+        }
+    }
+
+    pub fn ip_mut(&mut self, version: IpVersion) -> &mut IpState {
+        match version {
+            IpVersion::V4 => &mut self.ipv4,
+            IpVersion::V6 => &mut self.ipv6,
+        }
+    }
+
+    /// The state file used when none is given, `dns-cli-config.json` gets `dns-cli-config.state.json`
+    pub fn default_path(config_path: &Path) -> PathBuf {
+        let stem = config_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "dns-cli".to_string());
+        config_path.with_file_name(format!("{}.state.json", stem))
+    }
+
+    /// Loads the state file. When it does not exist yet and `legacy` is set, the files older
+    /// versions kept in the home dir are picked up.
+    pub fn load(path: &Path, legacy: bool) -> Result<State, DnsCliError> {
+        if !path.exists() {
+            return Ok(match legacy {
+                true => State::from_legacy_files(),
+                false => State::default(),
+            });
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            DnsCliError::Config(format!("Could not parse state file {:?}: {}", path, e))
+        })
+    }
+
+    /// Writes to a temporary file first and renames it over the state file, so the state file is
+    /// never left half written
+    pub fn save(&self, path: &Path) -> Result<(), DnsCliError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DnsCliError::Config(format!("Could not serialize state: {}", e)))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        {
+            use std::io::Write;
+            let mut file = std::fs::File::create(&tmp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Reads `~/.last_ip.txt`, `~/.last_ipv6.txt` and the failed record files older versions wrote
+    fn from_legacy_files() -> State {
+        let mut state = State::default();
+        let Some(home) = home::home_dir() else {
+            return state;
+        };
+        for version in IpVersion::ALL {
+            let (ip_file, failures_file) = legacy_file_names(version);
+            let ip_state = state.ip_mut(version);
+            if let Ok(content) = std::fs::read_to_string(home.join(ip_file)) {
+                ip_state.ip = parse_stored_ip(content);
+            }
+            if let Ok(content) = std::fs::read_to_string(home.join(failures_file)) {
+                ip_state.pending_failures = parse_failed_records(&content);
+            }
+        }
+        state
+    }
+}
+
+fn legacy_file_names(version: IpVersion) -> (&'static str, &'static str) {
+    match version {
+        IpVersion::V4 => (".last_ip.txt", ".failed_records_ipv4.txt"),
+        IpVersion::V6 => (".last_ipv6.txt", ".failed_records_ipv6.txt"),
+    }
+}
+
+fn parse_stored_ip(ip: String) -> Option<String> {
+    let trimmed = ip.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.to_string())
+}
+
+fn parse_failed_records(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_failed_records, parse_stored_ip, State};
+    use crate::ip_handler::IpVersion;
+    use std::path::Path;
+
+    #[test]
+    fn parse_stored_ip_trims_whitespace() {
+        assert_eq!(
+            parse_stored_ip("  203.0.113.7\n".to_string()),
+            Some("203.0.113.7".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_stored_ip_empty_becomes_none() {
+        assert_eq!(parse_stored_ip("   \n\t".to_string()), None);
+    }
+
+    #[test]
+    fn parse_failed_records_skips_blank_lines() {
+        assert_eq!(
+            parse_failed_records("work/abc\n\n  home/def \n"),
+            vec!["work/abc".to_string(), "home/def".to_string()]
+        );
+    }
+
+    #[test]
+    fn default_path_sits_next_to_the_config() {
+        assert_eq!(
+            State::default_path(Path::new("/etc/dns/work.json")),
+            Path::new("/etc/dns/work.state.json")
+        );
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("dns-cli-state-test-{}", std::process::id()));
+        let path = dir.join("config.state.json");
+        let mut state = State::default();
+        state.ip_mut(IpVersion::V6).ip = Some("2001:db8::1".to_string());
+        state.ipv4.pending_failures = vec!["work/abc".to_string()];
+        state.last_check = Some(chrono::Utc::now());
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path, false).unwrap(), state);
+        assert!(!dir.join("config.state.json.tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn other_configs_start_without_the_legacy_files() {
+        let path = std::env::temp_dir().join("dns-cli-state-test-missing.state.json");
+        assert_eq!(State::load(&path, false).unwrap(), State::default());
+    }
+}
@@ -0,0 +1,28 @@
+//! Records shared by the tests, tests change the fields they care about with struct update syntax
+
+use crate::config::{Record, RecordSettings, RecordType};
+use crate::dns_provider::DomainDetails;
+
+/// A record without an id, content or settings, like one just read from the config
+pub fn record(name: &str, record_type: RecordType) -> Record {
+    Record {
+        id: "".to_string(),
+        name: name.to_string(),
+        record_type,
+        content: None,
+        settings: RecordSettings::default(),
+    }
+}
+
+/// A record as a provider lists it, without an id and with the automatic TTL
+pub fn live(name: &str, record_type: &str, content: &str) -> DomainDetails {
+    DomainDetails {
+        id: "".to_string(),
+        name: name.to_string(),
+        record_type: record_type.to_string(),
+        content: content.to_string(),
+        proxied: false,
+        ttl: 1,
+        modified_on: None,
+    }
+}
@@ -1,12 +1,378 @@
+use crate::command_hook::CommandHook;
+use crate::config::RecordType;
 use crate::discord_webhook::DiscordWebhook;
+use crate::error::DnsCliError;
+use crate::http_webhook::HttpWebhook;
+use crate::ip_handler::IpVersion;
+use crate::output::{print_table, Output};
+use crate::smtp_notifier::SmtpNotifier;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What changed during a check, sent after the records were updated
+#[derive(Debug, Clone)]
+pub struct IpChange {
+    pub old_ip: String,
+    pub new_ip: String,
+    /// Names of the records that now point to the new IP
+    pub records_updated: Vec<String>,
+    /// Names of the records that could not be updated
+    pub failures: Vec<String>,
+}
+
+/// Something a check ran into that notifiers can be told about
+#[derive(Debug, Clone)]
+pub enum NotifyEvent {
+    IpChanged(IpChange),
+    RecordUpdated {
+        name: String,
+        record_type: RecordType,
+        ip: String,
+    },
+    RecordFailed {
+        name: String,
+        record_type: RecordType,
+        error: String,
+    },
+    IpDetectionFailed {
+        version: IpVersion,
+        error: String,
+    },
+    /// Reconciling found a record that points somewhere else than the IP
+    DriftDetected {
+        name: String,
+        live: String,
+        expected: String,
+    },
+}
+
+/// The kinds of events a notifier can subscribe to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum EventKind {
+    IpChanged,
+    RecordUpdated,
+    RecordFailed,
+    IpDetectionFailed,
+    DriftDetected,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EventKind::IpChanged => "ip_changed",
+            EventKind::RecordUpdated => "record_updated",
+            EventKind::RecordFailed => "record_failed",
+            EventKind::IpDetectionFailed => "ip_detection_failed",
+            EventKind::DriftDetected => "drift_detected",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The events a notifier gets when none are configured, the per record successes are left out
+pub fn default_events() -> Vec<EventKind> {
+    vec![
+        EventKind::IpChanged,
+        EventKind::RecordFailed,
+        EventKind::IpDetectionFailed,
+    ]
+}
+
+impl NotifyEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            NotifyEvent::IpChanged(_) => EventKind::IpChanged,
+            NotifyEvent::RecordUpdated { .. } => EventKind::RecordUpdated,
+            NotifyEvent::RecordFailed { .. } => EventKind::RecordFailed,
+            NotifyEvent::IpDetectionFailed { .. } => EventKind::IpDetectionFailed,
+            NotifyEvent::DriftDetected { .. } => EventKind::DriftDetected,
+        }
+    }
+
+    /// A made up event used to try out notifiers
+    pub fn sample(kind: EventKind) -> NotifyEvent {
+        let name = "test.example.com".to_string();
+        match kind {
+            EventKind::IpChanged => NotifyEvent::IpChanged(IpChange {
+                old_ip: "192.0.2.1".to_string(),
+                new_ip: "192.0.2.2".to_string(),
+                records_updated: vec![name],
+                failures: vec![],
+            }),
+            EventKind::RecordUpdated => NotifyEvent::RecordUpdated {
+                name,
+                record_type: RecordType::A,
+                ip: "192.0.2.2".to_string(),
+            },
+            EventKind::RecordFailed => NotifyEvent::RecordFailed {
+                name,
+                record_type: RecordType::A,
+                error: "API error: sample failure".to_string(),
+            },
+            EventKind::IpDetectionFailed => NotifyEvent::IpDetectionFailed {
+                version: IpVersion::V4,
+                error: "sample failure".to_string(),
+            },
+            EventKind::DriftDetected => NotifyEvent::DriftDetected {
+                name,
+                live: "192.0.2.1".to_string(),
+                expected: "192.0.2.2".to_string(),
+            },
+        }
+    }
+
+    /// A one line description of the event
+    pub fn message(&self) -> String {
+        match self {
+            NotifyEvent::IpChanged(change) => {
+                let mut message = format!("IP changed from {} to {}", change.old_ip, change.new_ip);
+                if !change.failures.is_empty() {
+                    message.push_str(&format!(
+                        ", failed to update {}",
+                        change.failures.join(", ")
+                    ));
+                }
+                message
+            }
+            NotifyEvent::RecordUpdated {
+                name,
+                record_type,
+                ip,
+            } => format!("Updated {} record {} to {}", record_type, name, ip),
+            NotifyEvent::RecordFailed {
+                name,
+                record_type,
+                error,
+            } => format!(
+                "Failed to update {} record {}: {}",
+                record_type, name, error
+            ),
+            NotifyEvent::IpDetectionFailed { version, error } => {
+                format!("Could not detect {}: {}", version, error)
+            }
+            NotifyEvent::DriftDetected {
+                name,
+                live,
+                expected,
+            } => format!("{} points to {} instead of {}", name, live, expected),
+        }
+    }
+
+    /// The values templates can use, placeholders that don't apply to the event are empty
+    pub fn placeholders(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("event", self.kind().to_string()),
+            ("message", self.message()),
+            ("hostname", local_hostname()),
+        ];
+        match self {
+            NotifyEvent::IpChanged(change) => {
+                values.push(("old_ip", change.old_ip.clone()));
+                values.push(("new_ip", change.new_ip.clone()));
+                values.push(("records_updated", change.records_updated.join(", ")));
+                values.push(("failures", change.failures.join(", ")));
+            }
+            NotifyEvent::RecordUpdated { name, ip, .. } => {
+                values.push(("record", name.clone()));
+                values.push(("new_ip", ip.clone()));
+                values.push(("records_updated", name.clone()));
+            }
+            NotifyEvent::RecordFailed { name, error, .. } => {
+                values.push(("record", name.clone()));
+                values.push(("error", error.clone()));
+                values.push(("failures", name.clone()));
+            }
+            NotifyEvent::IpDetectionFailed { error, .. } => {
+                values.push(("error", error.clone()));
+            }
+            NotifyEvent::DriftDetected {
+                name,
+                live,
+                expected,
+            } => {
+                values.push(("record", name.clone()));
+                values.push(("old_ip", live.clone()));
+                values.push(("new_ip", expected.clone()));
+            }
+        }
+        values
+    }
+}
+
+pub fn local_hostname() -> String {
+    gethostname::gethostname().to_string_lossy().to_string()
+}
 
 pub trait WebhookNotifier {
-    async fn change_ip(&self, from: &String, to: &String) -> ();
+    async fn notify(&self, event: &NotifyEvent);
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum WebhookNotifierType {
     DiscordWebhook(DiscordWebhook),
+    HttpWebhook(HttpWebhook),
+    SmtpNotifier(SmtpNotifier),
+    CommandHook(CommandHook),
 }
 
+impl WebhookNotifierType {
+    pub fn url(&self) -> &str {
+        match self {
+            WebhookNotifierType::DiscordWebhook(webhook) => webhook.url(),
+            WebhookNotifierType::HttpWebhook(webhook) => webhook.url(),
+            WebhookNotifierType::SmtpNotifier(smtp) => smtp.host(),
+            WebhookNotifierType::CommandHook(hook) => hook.command(),
+        }
+    }
+
+    /// Reads the secrets of the notifier that are kept in environment variables, files or the
+    /// OS keyring
+    pub fn resolve_secrets(&mut self) -> Result<(), DnsCliError> {
+        match self {
+            WebhookNotifierType::DiscordWebhook(webhook) => webhook.resolve_secrets(),
+            WebhookNotifierType::HttpWebhook(webhook) => webhook.resolve_secrets(),
+            WebhookNotifierType::SmtpNotifier(smtp) => smtp.resolve_secrets(),
+            WebhookNotifierType::CommandHook(_) => Ok(()),
+        }
+    }
+
+    /// The notifier with its secrets hidden, references to secrets are shown
+    pub fn redacted(&self) -> WebhookNotifierType {
+        match self {
+            WebhookNotifierType::DiscordWebhook(webhook) => {
+                WebhookNotifierType::DiscordWebhook(webhook.redacted())
+            }
+            WebhookNotifierType::HttpWebhook(webhook) => {
+                WebhookNotifierType::HttpWebhook(webhook.redacted())
+            }
+            WebhookNotifierType::SmtpNotifier(smtp) => {
+                WebhookNotifierType::SmtpNotifier(smtp.redacted())
+            }
+            WebhookNotifierType::CommandHook(hook) => {
+                WebhookNotifierType::CommandHook(hook.clone())
+            }
+        }
+    }
+
+    /// The event kinds this notifier is subscribed to
+    pub fn events(&self) -> &[EventKind] {
+        match self {
+            WebhookNotifierType::DiscordWebhook(webhook) => webhook.events(),
+            WebhookNotifierType::HttpWebhook(webhook) => webhook.events(),
+            WebhookNotifierType::SmtpNotifier(smtp) => smtp.events(),
+            WebhookNotifierType::CommandHook(hook) => hook.events(),
+        }
+    }
+
+    pub fn wants(&self, event: &NotifyEvent) -> bool {
+        self.events().contains(&event.kind())
+    }
+}
+
+/// A notifier with its number in `notify ls`, its secrets hidden
+#[derive(Serialize)]
+pub struct ListedNotifier {
+    number: usize,
+    #[serde(flatten)]
+    notifier: WebhookNotifierType,
+}
+
+/// The notifiers numbered as `notify rm` and `notify test` take them
+pub fn numbered(webhooks: &[WebhookNotifierType]) -> Vec<ListedNotifier> {
+    webhooks
+        .iter()
+        .enumerate()
+        .map(|(index, webhook)| ListedNotifier {
+            number: index + 1,
+            notifier: webhook.redacted(),
+        })
+        .collect()
+}
+
+impl ListedNotifier {
+    pub fn notifier(&self) -> &WebhookNotifierType {
+        &self.notifier
+    }
+}
+
+impl Output for Vec<ListedNotifier> {
+    fn print_table(&self) {
+        let rows: Vec<Vec<String>> = self
+            .iter()
+            .map(|listed| vec![listed.number.to_string(), listed.notifier.to_string()])
+            .collect();
+        print_table(&["#", "NOTIFIER"], &rows);
+    }
+}
+
+impl fmt::Display for WebhookNotifierType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.redacted() {
+            WebhookNotifierType::DiscordWebhook(webhook) => write!(f, "discord {}", webhook.url())?,
+            WebhookNotifierType::HttpWebhook(webhook) => {
+                write!(f, "http {} {}", webhook.method(), webhook.url())?
+            }
+            WebhookNotifierType::SmtpNotifier(smtp) => write!(
+                f,
+                "smtp {} to {}",
+                smtp.host(),
+                smtp.recipients().join(", ")
+            )?,
+            WebhookNotifierType::CommandHook(hook) => write!(f, "command {}", hook.command())?,
+        }
+        let events: Vec<String> = self.events().iter().map(EventKind::to_string).collect();
+        write!(f, " [{}]", events.join(", "))
+    }
+}
+
+impl WebhookNotifier for WebhookNotifierType {
+    async fn notify(&self, event: &NotifyEvent) {
+        match self {
+            WebhookNotifierType::DiscordWebhook(webhook) => webhook.notify(event).await,
+            WebhookNotifierType::HttpWebhook(webhook) => webhook.notify(event).await,
+            WebhookNotifierType::SmtpNotifier(smtp) => smtp.notify(event).await,
+            WebhookNotifierType::CommandHook(hook) => hook.notify(event).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_events, EventKind, NotifyEvent};
+
+    #[test]
+    fn ip_changed_message_mentions_failures() {
+        let NotifyEvent::IpChanged(mut change) = NotifyEvent::sample(EventKind::IpChanged) else {
+            unreachable!()
+        };
+        change.failures = vec!["b.example.com".to_string()];
+        assert_eq!(
+            NotifyEvent::IpChanged(change).message(),
+            "IP changed from 192.0.2.1 to 192.0.2.2, failed to update b.example.com"
+        );
+    }
+
+    #[test]
+    fn samples_have_the_requested_kind() {
+        for kind in [
+            EventKind::IpChanged,
+            EventKind::RecordUpdated,
+            EventKind::RecordFailed,
+            EventKind::IpDetectionFailed,
+            EventKind::DriftDetected,
+        ] {
+            assert_eq!(NotifyEvent::sample(kind).kind(), kind);
+        }
+    }
+
+    #[test]
+    fn event_kinds_use_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&default_events()).unwrap(),
+            r#"["ip_changed","record_failed","ip_detection_failed"]"#
+        );
+    }
+}
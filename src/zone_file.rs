@@ -0,0 +1,596 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::config::{Record, RecordSettings, RecordType};
+use crate::desired_state::PlannedRecord;
+use crate::dns_provider::DomainDetails;
+use crate::error::DnsCliError;
+use crate::record_content::{validate_record, RecordContent};
+
+/// Cloudflare reports an automatic TTL as 1, which is 300 seconds
+const AUTO_TTL: u32 = 300;
+
+/// The longest string a TXT record can hold, longer texts are split into several strings
+const TXT_STRING_LEN: usize = 255;
+
+/// A record read from a zone file with its owner name and the host names in its data made
+/// absolute
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneRecord {
+    pub name: String,
+    pub ttl: Option<u32>,
+    pub record_type: String,
+    /// The fields after the type, quoted strings keep their quotes and host names end with a dot
+    pub rdata: Vec<String>,
+    /// The line the record starts on, for error messages
+    pub line: usize,
+}
+
+/// The records of a zone file and the origin it starts with
+#[derive(Debug)]
+pub struct ZoneFile {
+    /// The first `$ORIGIN` of the file, or the origin it was parsed with
+    pub origin: Option<String>,
+    pub records: Vec<ZoneRecord>,
+}
+
+/// Writes records as an RFC 1035 zone file, names are relative to the zone and host names in
+/// the content end with a dot
+pub fn export(zone: &str, records: &[DomainDetails]) -> String {
+    let zone = zone.trim_end_matches('.');
+    let mut records: Vec<&DomainDetails> = records.iter().collect();
+    records.sort_by(|a, b| {
+        (a.name.to_lowercase(), &a.record_type).cmp(&(b.name.to_lowercase(), &b.record_type))
+    });
+    let mut text = format!("$ORIGIN {}.\n", zone);
+    for record in records {
+        let ttl = match record.ttl {
+            1 => AUTO_TTL,
+            ttl => ttl,
+        };
+        text.push_str(&format!(
+            "{}\t{}\tIN\t{}\t{}\n",
+            owner(&record.name, zone),
+            ttl,
+            record.record_type,
+            export_rdata(&record.record_type, &record.content)
+        ));
+    }
+    text
+}
+
+fn owner(name: &str, zone: &str) -> String {
+    let name = name.trim_end_matches('.');
+    if name.eq_ignore_ascii_case(zone) {
+        return "@".to_string();
+    }
+    match name.len().checked_sub(zone.len() + 1) {
+        Some(end) if name[end..].starts_with('.') && name[end + 1..].eq_ignore_ascii_case(zone) => {
+            name[..end].to_string()
+        }
+        _ => format!("{}.", name),
+    }
+}
+
+/// Providers leave out the trailing dot of host names and the quotes of TXT records
+fn export_rdata(record_type: &str, content: &str) -> String {
+    let fqdn = |host: &str| match host {
+        "." => ".".to_string(),
+        host if host.ends_with('.') => host.to_string(),
+        host => format!("{}.", host),
+    };
+    let mut fields: Vec<&str> = content.split_whitespace().collect();
+    match record_type {
+        "CNAME" | "NS" | "PTR" | "DNAME" => fqdn(content.trim()),
+        "MX" | "SRV" if !fields.is_empty() => {
+            let host = fqdn(fields.pop().unwrap_or_default());
+            fields.push(&host);
+            fields.join(" ")
+        }
+        "TXT" | "SPF" if !content.starts_with('"') => content
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks(TXT_STRING_LEN)
+            .map(|chunk| quote(&chunk.iter().collect::<String>()))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => content.to_string(),
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads a zone file. Names without a trailing dot are relative to the `$ORIGIN`, or to
+/// `origin` before the file sets one. `$INCLUDE` is not supported.
+pub fn parse(text: &str, origin: Option<&str>) -> Result<ZoneFile, DnsCliError> {
+    let mut first_origin = origin.map(|o| o.trim_end_matches('.').to_string());
+    let mut origin = first_origin.clone();
+    let mut default_ttl = None;
+    let mut last_owner: Option<String> = None;
+    let mut last_ttl = None;
+    let mut records = vec![];
+    for (line, blank_owner, mut fields) in entries(text)? {
+        let invalid = |reason: String| {
+            DnsCliError::Config(format!("Invalid zone file, line {}: {}", line, reason))
+        };
+        match fields[0].to_uppercase().as_str() {
+            "$ORIGIN" => {
+                let name = fields
+                    .get(1)
+                    .ok_or_else(|| invalid("$ORIGIN without a name".into()))?;
+                let name = absolute_name(name, origin.as_deref()).map_err(invalid)?;
+                first_origin.get_or_insert(name.clone());
+                origin = Some(name);
+                continue;
+            }
+            "$TTL" => {
+                let ttl = fields
+                    .get(1)
+                    .ok_or_else(|| invalid("$TTL without a value".into()))?;
+                default_ttl =
+                    Some(parse_ttl(ttl).ok_or_else(|| invalid(format!("{:?} is not a TTL", ttl)))?);
+                continue;
+            }
+            directive if directive.starts_with('$') => {
+                return Err(invalid(format!("{} is not supported", fields[0])));
+            }
+            _ => {}
+        }
+        let name = if blank_owner {
+            last_owner
+                .clone()
+                .ok_or_else(|| invalid("the first record has no name".into()))?
+        } else {
+            absolute_name(&fields.remove(0), origin.as_deref()).map_err(invalid)?
+        };
+        // The TTL and the class can come in either order before the type
+        let mut ttl = None;
+        for _ in 0..2 {
+            match fields.first() {
+                Some(field) if field.eq_ignore_ascii_case("IN") => {
+                    fields.remove(0);
+                }
+                Some(field) if ["CH", "HS", "CS"].contains(&field.to_uppercase().as_str()) => {
+                    return Err(invalid(format!("class {} is not supported", field)));
+                }
+                Some(field) if ttl.is_none() && parse_ttl(field).is_some() => {
+                    ttl = parse_ttl(&fields.remove(0));
+                }
+                _ => break,
+            }
+        }
+        if fields.is_empty() {
+            return Err(invalid("the record has no type".into()));
+        }
+        let record_type = fields.remove(0).to_uppercase();
+        if fields.is_empty() {
+            return Err(invalid(format!(
+                "the {} record has no content",
+                record_type
+            )));
+        }
+        // Host names in the data are relative to the $ORIGIN of this line, like the owner
+        let host_field = match record_type.as_str() {
+            "CNAME" | "NS" | "PTR" | "DNAME" => Some(0),
+            "MX" => Some(1),
+            "SRV" => Some(3),
+            _ => None,
+        };
+        if let Some(field) = host_field.and_then(|index| fields.get_mut(index)) {
+            if field != "." {
+                *field = format!(
+                    "{}.",
+                    absolute_name(field, origin.as_deref()).map_err(invalid)?
+                );
+            }
+        }
+        let ttl = ttl.or(default_ttl).or(last_ttl);
+        last_owner = Some(name.clone());
+        last_ttl = ttl;
+        records.push(ZoneRecord {
+            name,
+            ttl,
+            record_type,
+            rdata: fields,
+            line,
+        });
+    }
+    Ok(ZoneFile {
+        origin: first_origin,
+        records,
+    })
+}
+
+/// Splits a zone file into entries of fields, joining the lines in parentheses and dropping
+/// comments. Each entry has its line number and whether it starts with a blank, which means
+/// it has the owner of the entry before it.
+fn entries(text: &str) -> Result<Vec<(usize, bool, Vec<String>)>, DnsCliError> {
+    let mut entries = vec![];
+    let mut fields: Vec<String> = vec![];
+    let mut field = String::new();
+    let (mut line, mut start_line) = (1, 1);
+    let mut blank_owner = false;
+    let mut depth = 0;
+    let mut in_quotes = false;
+    let mut in_comment = false;
+    let mut at_line_start = true;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if at_line_start && depth == 0 {
+            start_line = line;
+            blank_owner = c == ' ' || c == '\t';
+        }
+        at_line_start = false;
+        if in_comment && c != '\n' {
+            continue;
+        }
+        match c {
+            '\\' => {
+                field.push(c);
+                if let Some(next) = chars.next() {
+                    field.push(next);
+                }
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                field.push(c);
+            }
+            _ if in_quotes && c != '\n' => field.push(c),
+            ';' => in_comment = true,
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' => {
+                return Err(DnsCliError::Config(format!(
+                    "Invalid zone file, line {}: ) without (",
+                    line
+                )))
+            }
+            ' ' | '\t' | '\r' | '\n' => {
+                if !field.is_empty() {
+                    fields.push(std::mem::take(&mut field));
+                }
+                if c == '\n' {
+                    if in_quotes {
+                        return Err(DnsCliError::Config(format!(
+                            "Invalid zone file, line {}: unterminated quote",
+                            line
+                        )));
+                    }
+                    line += 1;
+                    in_comment = false;
+                    at_line_start = true;
+                    if depth == 0 && !fields.is_empty() {
+                        entries.push((start_line, blank_owner, std::mem::take(&mut fields)));
+                    }
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes || depth > 0 {
+        return Err(DnsCliError::Config(format!(
+            "Invalid zone file, line {}: unterminated {}",
+            start_line,
+            if in_quotes { "quote" } else { "parentheses" }
+        )));
+    }
+    if !field.is_empty() {
+        fields.push(field);
+    }
+    if !fields.is_empty() {
+        entries.push((start_line, blank_owner, fields));
+    }
+    Ok(entries)
+}
+
+/// A name without its trailing dot, relative names are put under the origin
+fn absolute_name(name: &str, origin: Option<&str>) -> Result<String, String> {
+    if let Some(name) = name.strip_suffix('.') {
+        return Ok(name.to_string());
+    }
+    let origin = origin.ok_or_else(|| {
+        format!(
+            "{} is relative but there is no $ORIGIN, give the zone with --zone",
+            name
+        )
+    })?;
+    if name == "@" {
+        return Ok(origin.to_string());
+    }
+    Ok(format!("{}.{}", name, origin))
+}
+
+/// Seconds, or a BIND duration like `1h30m`
+fn parse_ttl(text: &str) -> Option<u32> {
+    if let Ok(seconds) = text.parse() {
+        return Some(seconds);
+    }
+    let mut total: u32 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        let value: u32 = std::mem::take(&mut number).parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    number.is_empty().then_some(total)
+}
+
+/// Reads a quoted or bare string, resolving `\"`, `\\` and `\DDD` escapes
+fn unquote(field: &str) -> String {
+    let inner = field
+        .strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field);
+    let mut text = String::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        let digits: String = std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_digit()))
+            .take(3)
+            .collect();
+        match digits.parse::<u8>() {
+            Ok(code) if digits.len() == 3 => text.push(code as char),
+            _ => {
+                text.push_str(&digits);
+                if digits.is_empty() {
+                    text.extend(chars.next());
+                }
+            }
+        }
+    }
+    text
+}
+
+impl ZoneRecord {
+    /// The record to create, None for types dns-cli does not manage like NS and SOA
+    pub fn plan(&self) -> Result<Option<PlannedRecord>, DnsCliError> {
+        let Some(record_type) = RecordType::from_api_name(&self.record_type) else {
+            return Ok(None);
+        };
+        let invalid = |reason: String| {
+            DnsCliError::Config(format!(
+                "Invalid {} record {} on line {}: {}",
+                record_type, self.name, self.line, reason
+            ))
+        };
+        let host = |field: &str| match field {
+            "." => ".".to_string(),
+            field => field.trim_end_matches('.').to_string(),
+        };
+        let number = |index: usize, what: &str| -> Result<u16, DnsCliError> {
+            self.rdata
+                .get(index)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| invalid(format!("{} is missing or not a number", what)))
+        };
+        let expect_fields = |count: usize| {
+            if self.rdata.len() == count {
+                Ok(())
+            } else {
+                Err(invalid(format!(
+                    "expected {} fields, got {}",
+                    count,
+                    self.rdata.len()
+                )))
+            }
+        };
+        let content = match record_type {
+            RecordType::A | RecordType::AAAA => {
+                expect_fields(1)?;
+                let ip = &self.rdata[0];
+                let valid = match record_type {
+                    RecordType::A => ip.parse::<Ipv4Addr>().is_ok(),
+                    _ => ip.parse::<Ipv6Addr>().is_ok(),
+                };
+                if !valid {
+                    return Err(invalid(format!("{:?} is not an IP address", ip)));
+                }
+                None
+            }
+            RecordType::CNAME => {
+                expect_fields(1)?;
+                Some(RecordContent {
+                    value: host(&self.rdata[0]),
+                    ..Default::default()
+                })
+            }
+            RecordType::MX => {
+                expect_fields(2)?;
+                Some(RecordContent {
+                    value: host(&self.rdata[1]),
+                    priority: Some(number(0, "the priority")?),
+                    ..Default::default()
+                })
+            }
+            RecordType::SRV => {
+                expect_fields(4)?;
+                Some(RecordContent {
+                    value: host(&self.rdata[3]),
+                    priority: Some(number(0, "the priority")?),
+                    weight: Some(number(1, "the weight")?),
+                    port: Some(number(2, "the port")?),
+                    ..Default::default()
+                })
+            }
+            RecordType::TXT => Some(RecordContent {
+                value: self.rdata.iter().map(|field| unquote(field)).collect(),
+                ..Default::default()
+            }),
+            RecordType::CAA => {
+                expect_fields(3)?;
+                let flags = number(0, "the flags")?;
+                Some(RecordContent {
+                    value: unquote(&self.rdata[2]),
+                    flags: Some(u8::try_from(flags).map_err(|_| invalid("flags over 255".into()))?),
+                    tag: Some(self.rdata[1].to_lowercase()),
+                    ..Default::default()
+                })
+            }
+        };
+        validate_record(record_type, &self.name, content.as_ref())?;
+        let value = match &content {
+            Some(content) => content.rdata(record_type),
+            None => self.rdata[0].clone(),
+        };
+        Ok(Some(PlannedRecord {
+            record: Record {
+                id: "".to_string(),
+                name: self.name.clone(),
+                record_type,
+                content,
+                settings: RecordSettings {
+                    ttl: self.ttl,
+                    ..Default::default()
+                },
+            },
+            value,
+            dynamic: false,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, parse, parse_ttl};
+    use crate::dns_provider::DomainDetails;
+    use crate::test_fixtures::live;
+
+    #[test]
+    fn parses_directives_parentheses_comments_and_blank_owners() {
+        let text = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN SOA ns1.example.net. admin.example.com. (
+        2024010101 ; serial
+        7200 3600 1209600 300 )
+    IN  MX  10 mail
+www 300 IN CNAME @
+    IN  TXT "a;b" "c"
+_sip._tcp IN 600 SRV 10 5 5060 sip.example.net.
+"#;
+        let zone = parse(text, None).unwrap();
+        assert_eq!(zone.origin.as_deref(), Some("example.com"));
+        let records: Vec<(&str, Option<u32>, &str)> = zone
+            .records
+            .iter()
+            .map(|r| (r.name.as_str(), r.ttl, r.record_type.as_str()))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                ("example.com", Some(3600), "SOA"),
+                ("example.com", Some(3600), "MX"),
+                ("www.example.com", Some(300), "CNAME"),
+                ("www.example.com", Some(3600), "TXT"),
+                ("_sip._tcp.example.com", Some(600), "SRV"),
+            ]
+        );
+        assert_eq!(zone.records[0].rdata.len(), 7);
+
+        assert!(zone.records[0].plan().unwrap().is_none());
+        let mx = zone.records[1].plan().unwrap().unwrap();
+        assert_eq!(mx.value, "10 mail.example.com");
+        let cname = zone.records[2].plan().unwrap().unwrap();
+        assert_eq!(cname.value, "example.com");
+        let txt = zone.records[3].plan().unwrap().unwrap();
+        assert_eq!(txt.value, "\"a;bc\"");
+    }
+
+    #[test]
+    fn relative_names_need_an_origin() {
+        assert!(parse("www IN A 192.0.2.1\n", None).is_err());
+        let zone = parse("www IN A 192.0.2.1\n", Some("example.com.")).unwrap();
+        assert_eq!(zone.records[0].name, "www.example.com");
+        assert!(parse("www IN A 192.0.2.1\n$INCLUDE other.zone\n", Some("a")).is_err());
+    }
+
+    #[test]
+    fn data_names_are_relative_to_the_origin_of_their_line() {
+        let text = "$ORIGIN example.com.\n\
+                    www IN CNAME web\n\
+                    $ORIGIN office.example.com.\n\
+                    @ IN MX 10 mail\n\
+                    _sip._tcp IN SRV 10 5 5060 pbx\n";
+        let zone = parse(text, None).unwrap();
+        let values: Vec<String> = zone
+            .records
+            .iter()
+            .map(|r| r.plan().unwrap().unwrap().value)
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "web.example.com",
+                "10 mail.office.example.com",
+                "10 5 5060 pbx.office.example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn ttl_takes_seconds_and_units() {
+        assert_eq!(parse_ttl("300"), Some(300));
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1d"), Some(86400));
+        assert_eq!(parse_ttl("IN"), None);
+        assert_eq!(parse_ttl("10x"), None);
+    }
+
+    #[test]
+    fn export_round_trips_through_parse() {
+        let records = [
+            live("example.com", "MX", "10 mail.example.com"),
+            DomainDetails {
+                ttl: 300,
+                ..live("www.example.com", "CNAME", "example.com")
+            },
+            DomainDetails {
+                ttl: 3600,
+                ..live("example.com", "TXT", "v=spf1 \"quoted\" -all")
+            },
+            DomainDetails {
+                ttl: 600,
+                ..live("other.org", "A", "192.0.2.1")
+            },
+        ];
+        let text = export("example.com", &records);
+        assert_eq!(
+            text,
+            "$ORIGIN example.com.\n\
+             @\t300\tIN\tMX\t10 mail.example.com.\n\
+             @\t3600\tIN\tTXT\t\"v=spf1 \\\"quoted\\\" -all\"\n\
+             other.org.\t600\tIN\tA\t192.0.2.1\n\
+             www\t300\tIN\tCNAME\texample.com.\n"
+        );
+        let zone = parse(&text, None).unwrap();
+        let values: Vec<String> = zone
+            .records
+            .iter()
+            .map(|r| r.plan().unwrap().unwrap().value)
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "10 mail.example.com",
+                "\"v=spf1 \\\"quoted\\\" -all\"",
+                "192.0.2.1",
+                "example.com"
+            ]
+        );
+    }
+}